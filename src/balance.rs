@@ -0,0 +1,49 @@
+use crate::models::{CreditNote, Payment};
+use crate::money::Money;
+use crate::tax::{self, InvoiceTotals};
+use serde::Serialize;
+
+/// How much of an invoice has been paid or credited and how much is still owed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Balance {
+    pub total: Money,
+    /// Total of credit notes issued against the invoice, including tax
+    pub amount_credited: Money,
+    pub amount_paid: Money,
+    /// Negative if the client has overpaid
+    pub balance_due: Money,
+}
+
+impl Balance {
+    pub fn is_settled(&self) -> bool {
+        self.balance_due.minor_units <= 0
+    }
+}
+
+/// Computes the outstanding balance of an invoice from its totals and the
+/// payments and credit notes recorded against it
+pub fn compute_balance(
+    totals: &InvoiceTotals,
+    payments: &[Payment],
+    credit_notes: &[CreditNote],
+) -> Balance {
+    let currency = &totals.total.currency;
+    let amount_paid = payments
+        .iter()
+        .fold(Money::zero(currency), |paid, payment| {
+            paid + payment.amount.clone()
+        });
+    let amount_credited = credit_notes
+        .iter()
+        .fold(Money::zero(currency), |credited, credit_note| {
+            credited + tax::compute_totals(&credit_note.items, currency).total
+        });
+    let balance_due = totals.total.clone() - amount_credited.clone() - amount_paid.clone();
+
+    Balance {
+        total: totals.total.clone(),
+        amount_credited,
+        amount_paid,
+        balance_due,
+    }
+}
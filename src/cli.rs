@@ -1,4 +1,7 @@
-use clap::{Parser, Subcommand};
+use crate::models::{DocumentType, InvoiceStatus};
+use crate::pdf::Source;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 pub struct Cli {
@@ -6,6 +9,16 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
 
+    /// Database file to use, e.g. to keep a separate ledger per business.
+    /// Defaults to clinv.db in the user's data directory.
+    #[arg(long, global = true, env = "CLINV_DB", value_name = "PATH")]
+    pub db: Option<PathBuf>,
+
+    /// Config file to use. Defaults to clinv/config.toml in the user's config
+    /// directory.
+    #[arg(long, global = true, env = "CLINV_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
     /// Raw command input for natural language processing
     #[clap(flatten)]
     pub raw_command: RawCommandInput,
@@ -30,6 +43,46 @@ pub enum Commands {
         /// Phone number of the client
         #[arg(short, long)]
         phone_number: Option<String>,
+
+        /// Payment terms, e.g. net14 or receipt (defaults to net30)
+        #[arg(short = 't', long)]
+        payment_terms: Option<String>,
+
+        /// Template for this client's invoices, a name or path (defaults to
+        /// the configured template)
+        #[arg(long)]
+        template: Option<String>,
+    },
+
+    /// Edit an existing client, prompting for every field if no changes are given
+    EditClient {
+        /// Nickname of the client to edit
+        #[arg(short, long)]
+        client_nickname: Option<String>,
+
+        /// New name
+        #[arg(short = 'n', long)]
+        name: Option<String>,
+
+        /// New nickname
+        #[arg(short = 'k', long)]
+        nickname: Option<String>,
+
+        /// New email
+        #[arg(short, long)]
+        email: Option<String>,
+
+        /// New phone number
+        #[arg(short, long)]
+        phone_number: Option<String>,
+
+        /// New payment terms, e.g. net14, receipt or default
+        #[arg(short = 't', long)]
+        payment_terms: Option<String>,
+
+        /// New invoice template, a name or path, or default
+        #[arg(long)]
+        template: Option<String>,
     },
 
     /// Create a new invoice
@@ -37,16 +90,45 @@ pub enum Commands {
         /// Client name
         #[arg(short, long)]
         client_nickname: Option<String>,
+
+        /// Due date as YYYY-MM-DD, overriding the client's payment terms
+        #[arg(short, long)]
+        due_date: Option<String>,
+
+        /// Business profile issuing the invoice (defaults to the default profile)
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// An item as "description;hours;rate" or "description;hours;rate;tax_code".
+        /// Can be given more than once.
+        #[arg(short, long = "item", value_name = "ITEM")]
+        items: Vec<String>,
+
+        /// JSON, TOML or CSV file of items to add
+        #[arg(short = 'f', long)]
+        items_file: Option<PathBuf>,
     },
 
     /// List all clients
-    ListClients,
+    ListClients {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
 
     /// List all invoices
     ListInvoices {
         /// Client name
         #[arg(short, long)]
         client_nickname: Option<String>,
+
+        /// Only show invoices with this status (draft, issued, sent, paid, void)
+        #[arg(short, long)]
+        status: Option<InvoiceStatus>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 
     /// Delete a client
@@ -63,14 +145,477 @@ pub enum Commands {
         invoice_id: Option<String>,
     },
 
-    /// Generate a PDF for an invoice
+    /// Change the items on a draft invoice
+    Invoice {
+        #[command(subcommand)]
+        action: InvoiceCommands,
+    },
+
+    /// Issue a draft invoice, finalising it for sending
+    IssueInvoice {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+    },
+
+    /// Mark an issued invoice as sent to the client
+    MarkSent {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+    },
+
+    /// Mark a sent invoice as paid
+    MarkPaid {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+    },
+
+    /// Void an unpaid invoice
+    VoidInvoice {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+    },
+
+    /// Record a payment received against an invoice
+    RecordPayment {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// Amount received, e.g. 250.00
+        #[arg(short, long)]
+        amount: Option<String>,
+
+        /// Date received as YYYY-MM-DD (defaults to today)
+        #[arg(short, long)]
+        date: Option<String>,
+
+        /// How the payment was made, e.g. bank transfer
+        #[arg(short, long)]
+        method: Option<String>,
+
+        /// Bank or receipt reference
+        #[arg(short, long)]
+        reference: Option<String>,
+    },
+
+    /// List payments, optionally for a single invoice
+    ListPayments {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Create a new tax rate
+    NewTaxRate {
+        /// Short code used to select the rate, e.g. gst
+        #[arg(short, long)]
+        code: Option<String>,
+
+        /// Display name shown on invoices, e.g. GST
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Rate as a percentage, e.g. 10
+        #[arg(short, long)]
+        rate: Option<String>,
+    },
+
+    /// List all tax rates
+    ListTaxRates {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Manage the business profiles printed on invoices. On its own, shows
+    /// the default profile.
+    Profile {
+        #[command(subcommand)]
+        action: Option<ProfileCommands>,
+    },
+
+    /// Issue, list and print credit notes against invoices
+    CreditNote {
+        #[command(subcommand)]
+        action: CreditNoteCommands,
+    },
+
+    /// Show or configure how invoices and credit notes are numbered
+    Numbering {
+        #[command(subcommand)]
+        action: NumberingCommands,
+    },
+
+    /// Run a report
+    Report {
+        #[command(subcommand)]
+        report: ReportCommands,
+    },
+
+    /// Generate a PDF (or HTML, text or Markdown) for an invoice
     Generate {
         /// invoice ID
         #[arg(short, long)]
         invoice_id: Option<String>,
+
+        /// What to write; only pdf needs a PDF backend
+        #[arg(long, value_enum, default_value_t = DocumentFormat::Pdf)]
+        output_format: DocumentFormat,
+
+        /// Template name or path, instead of the client's or the configured
+        /// one
+        #[arg(short, long)]
+        template: Option<String>,
+    },
+
+    /// Show or change settings in the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+
+    /// Manage invoice templates
+    #[command(alias = "template")]
+    Templates {
+        #[command(subcommand)]
+        action: TemplateCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommands {
+    /// List the built-in template and the named templates in the template
+    /// directory
+    List {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Write the built-in template to the template directory for customising
+    Export {
+        /// Name to save it under, as <name>.html
+        #[arg(default_value = "custom")]
+        name: String,
+
+        /// Export the credit note template instead
+        #[arg(long)]
+        credit_note: bool,
+
+        /// Replace the template if it already exists
+        #[arg(short, long)]
+        force: bool,
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Print the value of one setting
+    Get {
+        /// e.g. template, output_dir, payment_terms_days, phone_region or currency
+        key: String,
+    },
+
+    /// Change a setting and save it to the config file
+    Set { key: String, value: String },
+
+    /// Print every setting
+    List {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum InvoiceCommands {
+    /// Add an item to a draft invoice, prompting for anything not given
+    AddItem {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// What the item is for
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Hours worked (or quantity)
+        #[arg(long, allow_negative_numbers = true)]
+        hours: Option<f64>,
+
+        /// Rate per hour, e.g. 120.00
+        #[arg(short, long)]
+        rate: Option<String>,
+
+        /// Tax code (defaults to gst)
+        #[arg(short, long)]
+        tax_code: Option<String>,
+    },
+
+    /// Change an item on a draft invoice, prompting for every field if no changes are given
+    EditItem {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// item ID
+        #[arg(short = 'm', long)]
+        item_id: Option<String>,
+
+        /// New description
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// New hours
+        #[arg(long, allow_negative_numbers = true)]
+        hours: Option<f64>,
+
+        /// New rate
+        #[arg(short, long)]
+        rate: Option<String>,
+
+        /// New tax code
+        #[arg(short, long)]
+        tax_code: Option<String>,
+    },
+
+    /// Remove an item from a draft invoice
+    RemoveItem {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// item ID
+        #[arg(short = 'm', long)]
+        item_id: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CreditNoteCommands {
+    /// Issue a credit note against an invoice
+    New {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// Credit every item on the invoice instead of entering items
+        #[arg(short, long)]
+        full: bool,
+
+        /// Why the credit is being given
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+
+    /// List credit notes, optionally for a single invoice
+    List {
+        /// invoice ID
+        #[arg(short, long)]
+        invoice_id: Option<String>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Generate a PDF (or HTML, text or Markdown) for a credit note
+    Generate {
+        /// credit note ID
+        #[arg(short, long)]
+        credit_note_id: Option<String>,
+
+        /// What to write; only pdf needs a PDF backend
+        #[arg(long, value_enum, default_value_t = DocumentFormat::Pdf)]
+        output_format: DocumentFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NumberingCommands {
+    /// Show a profile's numbering
+    Show {
+        /// Business profile (defaults to the default profile)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Document type: invoice or credit-note
+        #[arg(long, default_value_t = DocumentType::Invoice)]
+        document: DocumentType,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Change a profile's numbering
+    Set {
+        /// Business profile (defaults to the default profile)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Document type: invoice or credit-note
+        #[arg(long, default_value_t = DocumentType::Invoice)]
+        document: DocumentType,
+
+        /// Text before the number, e.g. INV-
+        #[arg(short, long)]
+        prefix: Option<String>,
+
+        /// Minimum number of digits
+        #[arg(short = 'd', long)]
+        padding: Option<usize>,
+
+        /// Restart numbering each year, with the year in the number (true or false)
+        #[arg(short, long)]
+        yearly_reset: Option<bool>,
+
+        /// Number given to the next document issued
+        #[arg(short, long)]
+        next_number: Option<i64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommands {
+    /// Outstanding balances per client, bucketed by days overdue
+    Aging {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+
+        /// Age balances as at this date (YYYY-MM-DD, defaults to today)
+        #[arg(short, long)]
+        as_of: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum ProfileCommands {
+    /// List all business profiles
+    List {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Show a business profile
+    Show {
+        /// Profile name (defaults to the default profile)
+        name: Option<String>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Create or edit a business profile, prompting for every field if no flags are given
+    Edit {
+        /// Profile name (defaults to the default profile)
+        name: Option<String>,
+
+        /// Business or trading name
+        #[arg(short = 'n', long)]
+        business_name: Option<String>,
+
+        /// Australian Business Number
+        #[arg(short, long)]
+        abn: Option<String>,
+
+        /// Postal address, lines separated by newlines
+        #[arg(long)]
+        address: Option<String>,
+
+        /// Contact email
+        #[arg(short, long)]
+        email: Option<String>,
+
+        /// Contact phone number
+        #[arg(short, long)]
+        phone_number: Option<String>,
+
+        /// Name on the bank account payments go to
+        #[arg(long)]
+        bank_account_name: Option<String>,
+
+        /// Bank BSB
+        #[arg(long)]
+        bsb: Option<String>,
+
+        /// Bank account number
+        #[arg(long)]
+        account_number: Option<String>,
+
+        /// PayID for Osko payments, e.g. a phone number or email
+        #[arg(long)]
+        payid: Option<String>,
+
+        /// Path to a logo image
+        #[arg(short, long)]
+        logo: Option<String>,
+    },
+
+    /// Use a profile for new invoices when none is given
+    SetDefault {
+        /// Profile name
+        name: String,
+    },
+}
+
+/// How listings and reports are written to stdout
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// For reading in a terminal
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+/// What `generate` writes
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// Rendered by the configured PDF backend
+    #[default]
+    Pdf,
+    /// The filled-in HTML template, e.g. to paste into an email
+    Html,
+    /// Plain text, for reading in a terminal
+    Txt,
+    Md,
+}
+
+impl DocumentFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentFormat::Pdf => "pdf",
+            DocumentFormat::Html => "html",
+            DocumentFormat::Txt => "txt",
+            DocumentFormat::Md => "md",
+        }
+    }
+
+    /// The template to fill in, or `None` for a PDF, where the backend
+    /// decides
+    pub fn source(&self) -> Option<Source> {
+        match self {
+            DocumentFormat::Pdf => None,
+            DocumentFormat::Html => Some(Source::Html),
+            DocumentFormat::Txt => Some(Source::Text),
+            DocumentFormat::Md => Some(Source::Markdown),
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct RawCommandInput {
     /// The raw command words (only used if no structured command is provided)
@@ -91,30 +636,158 @@ pub fn map_command_words(words: &[String]) -> Option<Commands> {
                 nickname: None,
                 email: None,
                 phone_number: None,
+                payment_terms: None,
+                template: None,
+            })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "edit" && s2.to_lowercase() == "client" => {
+            Some(Commands::EditClient {
+                client_nickname: rest.first().cloned(),
+                name: None,
+                nickname: None,
+                email: None,
+                phone_number: None,
+                payment_terms: None,
+                template: None,
             })
         }
         [s1, s2, rest @ ..] if s1.to_lowercase() == "new" && s2.to_lowercase() == "invoice" => {
-            let client_nickname = rest.get(0).map(|s| s.clone());
-            Some(Commands::NewInvoice { client_nickname })
+            let client_nickname = rest.first().cloned();
+            Some(Commands::NewInvoice {
+                client_nickname,
+                due_date: None,
+                profile: None,
+                items: Vec::new(),
+                items_file: None,
+            })
         }
         [s1, s2] if s1.to_lowercase() == "list" && s2.to_lowercase() == "clients" => {
-            Some(Commands::ListClients)
+            Some(Commands::ListClients {
+                format: OutputFormat::Table,
+            })
         }
         [s1, s2, rest @ ..] if s1.to_lowercase() == "list" && s2.to_lowercase() == "invoices" => {
-            let client_nickname = rest.get(0).map(|s| s.clone());
-            Some(Commands::ListInvoices { client_nickname })
+            let client_nickname = rest.first().cloned();
+            Some(Commands::ListInvoices {
+                client_nickname,
+                status: None,
+                format: OutputFormat::Table,
+            })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "add" && s2.to_lowercase() == "item" => {
+            Some(Commands::Invoice {
+                action: InvoiceCommands::AddItem {
+                    invoice_id: rest.first().cloned(),
+                    description: None,
+                    hours: None,
+                    rate: None,
+                    tax_code: None,
+                },
+            })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "remove" && s2.to_lowercase() == "item" => {
+            Some(Commands::Invoice {
+                action: InvoiceCommands::RemoveItem {
+                    invoice_id: rest.first().cloned(),
+                    item_id: rest.get(1).cloned(),
+                },
+            })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "issue" && s2.to_lowercase() == "invoice" => {
+            let invoice_id = rest.first().cloned();
+            Some(Commands::IssueInvoice { invoice_id })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "mark" && s2.to_lowercase() == "sent" => {
+            let invoice_id = rest.first().cloned();
+            Some(Commands::MarkSent { invoice_id })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "mark" && s2.to_lowercase() == "paid" => {
+            let invoice_id = rest.first().cloned();
+            Some(Commands::MarkPaid { invoice_id })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "void" && s2.to_lowercase() == "invoice" => {
+            let invoice_id = rest.first().cloned();
+            Some(Commands::VoidInvoice { invoice_id })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "record" && s2.to_lowercase() == "payment" => {
+            Some(Commands::RecordPayment {
+                invoice_id: rest.first().cloned(),
+                amount: rest.get(1).cloned(),
+                date: None,
+                method: None,
+                reference: None,
+            })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "list" && s2.to_lowercase() == "payments" => {
+            let invoice_id = rest.first().cloned();
+            Some(Commands::ListPayments {
+                invoice_id,
+                format: OutputFormat::Table,
+            })
+        }
+        [s1, s2, s3, rest @ ..]
+            if s1.to_lowercase() == "new"
+                && s2.to_lowercase() == "tax"
+                && s3.to_lowercase() == "rate" =>
+        {
+            Some(Commands::NewTaxRate {
+                code: rest.first().cloned(),
+                name: None,
+                rate: None,
+            })
+        }
+        [s1, s2, s3]
+            if s1.to_lowercase() == "list"
+                && s2.to_lowercase() == "tax"
+                && s3.to_lowercase() == "rates" =>
+        {
+            Some(Commands::ListTaxRates {
+                format: OutputFormat::Table,
+            })
+        }
+        [s1, s2, rest @ ..] if s1.to_lowercase() == "credit" && s2.to_lowercase() == "note" => {
+            Some(Commands::CreditNote {
+                action: CreditNoteCommands::New {
+                    invoice_id: rest.first().cloned(),
+                    full: false,
+                    reason: None,
+                },
+            })
+        }
+        [s1, s2, s3, rest @ ..]
+            if s1.to_lowercase() == "list"
+                && s2.to_lowercase() == "credit"
+                && s3.to_lowercase() == "notes" =>
+        {
+            Some(Commands::CreditNote {
+                action: CreditNoteCommands::List {
+                    invoice_id: rest.first().cloned(),
+                    format: OutputFormat::Table,
+                },
+            })
         }
         [s1, s2, rest @ ..] if s1.to_lowercase() == "delete" && s2.to_lowercase() == "client" => {
-            let client_nickname = rest.get(0).map(|s| s.clone());
+            let client_nickname = rest.first().cloned();
             Some(Commands::DeleteClient { client_nickname })
         }
         [s1, s2, rest @ ..] if s1.to_lowercase() == "delete" && s2.to_lowercase() == "invoice" => {
-            let id = rest.get(0).map(|s| s.clone());
+            let id = rest.first().cloned();
             Some(Commands::DeleteInvoice { invoice_id: id })
         }
+        [s1, s2] if s1.to_lowercase() == "list" && s2.to_lowercase() == "profiles" => {
+            Some(Commands::Profile {
+                action: Some(ProfileCommands::List {
+                    format: OutputFormat::Table,
+                }),
+            })
+        }
         [s1, rest @ ..] if s1.to_lowercase() == "generate" => {
-            let id = rest.get(0).map(|s| s.clone());
-            Some(Commands::Generate { invoice_id: id })
+            let id = rest.first().cloned();
+            Some(Commands::Generate {
+                invoice_id: id,
+                output_format: DocumentFormat::Pdf,
+                template: None,
+            })
         }
         _ => None,
     }
@@ -1,19 +1,64 @@
-use crate::cli::Commands;
+use crate::balance;
+use crate::cli::{
+    Commands, ConfigCommands, CreditNoteCommands, DocumentFormat, InvoiceCommands,
+    NumberingCommands, OutputFormat, ProfileCommands, ReportCommands, TemplateCommands,
+};
+use crate::config::Config;
 use crate::database;
+use crate::error::Error;
+use crate::items;
+use crate::models::{
+    BusinessProfile, DocumentType, InvoiceItem, InvoiceStatus, NewItem, DEFAULT_PROFILE_NAME,
+};
+use crate::money::Money;
+use crate::output::{self, CreditNoteListing, InvoiceListing};
+use crate::pdf;
+use crate::reports;
+use crate::tax;
+use crate::template::{self, Template};
 use crate::utils;
 use crate::utils::generate_pdf;
 use chrono::prelude::*;
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension};
+use std::io;
+use std::path::{Path, PathBuf};
 
-pub fn execute_command(connection: &Connection, command: Commands) -> Result<()> {
+/// What a command that succeeded did, so callers can tell without parsing its
+/// output
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Information was printed and nothing was changed
+    Printed,
+    /// A record was created with this id
+    Created(i64),
+    /// An existing record was changed, or a setting saved
+    Saved,
+    Deleted,
+    /// A document was written to this path
+    Generated(PathBuf),
+}
+
+/// Runs a command. `config` supplies the defaults for anything not given on
+/// the command line.
+pub fn execute_command(
+    connection: &Connection,
+    config: &Config,
+    command: Commands,
+) -> Result<Outcome, Error> {
     match command {
         Commands::NewClient {
             name,
             nickname,
             email,
             phone_number,
+            payment_terms,
+            template: client_template,
         } => {
             println!("Creating new client...");
+            let client_template = client_template
+                .map(|t| parse_client_template(&t, config))
+                .transpose()?
+                .flatten();
 
             // Prompt for fields if not provided
             let name = name.unwrap_or_else(|| utils::prompt_for_str("Enter client name: "));
@@ -27,18 +72,125 @@ pub fn execute_command(connection: &Connection, command: Commands) -> Result<()>
 
             let mut phone_number = phone_number
                 .unwrap_or_else(|| utils::prompt_for_str("Enter client phone number: "));
-            while !utils::is_valid_phone(&phone_number) {
+            while !utils::is_valid_phone(&phone_number, &config.phone_region) {
                 println!("Not a valid phone number");
                 phone_number = utils::prompt_for_str("Enter client phone number: ");
             }
 
+            // Clients without terms use the default
+            let payment_terms_days = match payment_terms {
+                Some(mut payment_terms) => loop {
+                    match utils::parse_payment_terms(&payment_terms) {
+                        Some(days) => break Some(days),
+                        None => {
+                            println!("Not valid payment terms");
+                            payment_terms = utils::prompt_for_str(
+                                "Enter payment terms (e.g. net14, receipt): ",
+                            );
+                        }
+                    }
+                },
+                None => None,
+            };
+
             // Create and notify
-            database::new_client(connection, &name, &nickname, &email, &phone_number)?;
+            let client_id = database::new_client(
+                connection,
+                &name,
+                &nickname,
+                &email,
+                &phone_number,
+                payment_terms_days,
+            )?;
+            if let Some(ref client_template) = client_template {
+                database::set_client_template(connection, &nickname, Some(client_template))?;
+            }
             println!("Created client: {} <{}> <{}>", name, email, phone_number);
 
-            Ok(())
+            Ok(Outcome::Created(client_id))
         }
-        Commands::NewInvoice { client_nickname } => {
+        Commands::EditClient {
+            client_nickname,
+            name,
+            nickname,
+            email,
+            phone_number,
+            payment_terms,
+            template: client_template,
+        } => {
+            let client_nickname =
+                client_nickname.unwrap_or_else(|| utils::prompt_for_str("Enter client nickname: "));
+            let client = database::get_client(connection, &client_nickname)?
+                .ok_or_else(|| Error::ClientNotFound(client_nickname.clone()))?;
+            println!("Editing client: {}...", client.nickname);
+
+            let interactive = name.is_none()
+                && nickname.is_none()
+                && email.is_none()
+                && phone_number.is_none()
+                && payment_terms.is_none()
+                && client_template.is_none();
+            let (name, nickname, email, phone_number, payment_terms_days) = if interactive {
+                let current_terms = client
+                    .payment_terms_days
+                    .map_or("default".to_string(), utils::format_payment_terms);
+                let payment_terms_days = loop {
+                    let terms = utils::prompt_with_default("Payment terms", &current_terms);
+                    match parse_client_payment_terms(&terms) {
+                        Some(days) => break days,
+                        None => println!("Not valid payment terms"),
+                    }
+                };
+                (
+                    utils::prompt_with_default("Name", &client.name),
+                    utils::prompt_with_default("Nickname", &client.nickname),
+                    utils::prompt_with_default("Email", &client.email),
+                    utils::prompt_with_default("Phone number", &client.phone_number),
+                    payment_terms_days,
+                )
+            } else {
+                // Only the given fields change
+                let payment_terms_days = match payment_terms {
+                    Some(terms) => parse_client_payment_terms(&terms).ok_or_else(|| {
+                        Error::Validation(format!("Not valid payment terms: {}", terms))
+                    })?,
+                    None => client.payment_terms_days,
+                };
+                (
+                    name.unwrap_or(client.name),
+                    nickname.unwrap_or(client.nickname),
+                    email.unwrap_or(client.email),
+                    phone_number.unwrap_or(client.phone_number),
+                    payment_terms_days,
+                )
+            };
+
+            // Update and notify
+            database::update_client(
+                connection,
+                &client_nickname,
+                &name,
+                &nickname,
+                &email,
+                &phone_number,
+                payment_terms_days,
+                &config.phone_region,
+            )?;
+            if let Some(client_template) = client_template {
+                let client_template = parse_client_template(&client_template, config)?;
+                database::set_client_template(connection, &nickname, client_template.as_deref())?;
+            }
+            println!("Updated client: {} <{}> <{}>", name, email, phone_number);
+
+            Ok(Outcome::Saved)
+        }
+        Commands::NewInvoice {
+            client_nickname,
+            due_date,
+            profile: profile_name,
+            items: item_specs,
+            items_file,
+        } => {
             match client_nickname {
                 Some(ref client_nickname) => {
                     println!("Creating invoice for client: {}...", client_nickname);
@@ -53,41 +205,81 @@ pub fn execute_command(connection: &Connection, command: Commands) -> Result<()>
                 client_nickname.unwrap_or_else(|| utils::prompt_for_str("Enter client nickname: "));
             let local: DateTime<Local> = Local::now();
             let date_string = local.format("%Y-%m-%d").to_string();
+            if let Some(ref due_date) = due_date {
+                if NaiveDate::parse_from_str(due_date, "%Y-%m-%d").is_err() {
+                    return Err(Error::Validation(format!(
+                        "Not a valid due date, expected YYYY-MM-DD: {}",
+                        due_date
+                    )));
+                }
+            }
 
-            // Create invoice and notify
-            let invoice_id = database::new_invoice(connection, &client_nickname, &date_string)?;
+            // Items given up front are all checked before anything is saved
+            let mut new_items = Vec::new();
+            for (i, spec) in item_specs.iter().enumerate() {
+                new_items.push(items::parse_item_spec(spec, &config.currency, i + 1)?);
+            }
+            if let Some(ref path) = items_file {
+                new_items.extend(items::read_items_file(path, &config.currency)?);
+            }
+            items::check_tax_codes(connection, &new_items)?;
+
+            let profile_id = resolve_profile_id(connection, profile_name.as_deref())?;
+            if profile_id.is_none() {
+                println!("Warning: no business profile set up, run `clinv profile edit`");
+            }
+
+            // The invoice and its items are saved together or not at all
+            let transaction = connection.unchecked_transaction()?;
+            let invoice_id = database::new_invoice(
+                &transaction,
+                &client_nickname,
+                &date_string,
+                due_date.as_deref(),
+                profile_id,
+                config.payment_terms_days,
+                &config.currency,
+            )?;
+
+            // Get items for invoice and notify. Only prompt when none were given.
+            if item_specs.is_empty() && items_file.is_none() {
+                utils::read_and_add_invoice_items(&transaction, invoice_id)?;
+            } else {
+                for item in &new_items {
+                    database::add_invoice_item(
+                        &transaction,
+                        invoice_id,
+                        &item.description,
+                        item.hours,
+                        &item.rate,
+                        &item.tax_code,
+                    )?;
+                }
+            }
+            // Nothing is reported as created until it's saved
+            transaction.commit()?;
             println!(
                 "Created invoice with id: {}, for client: {} ",
                 invoice_id, client_nickname
             );
-
-            // Get items for invoice and notify
-            let _items = utils::read_and_add_invoice_items(connection, invoice_id);
             println!("Items added to invoice with id: {}", invoice_id);
 
-            Ok(())
+            Ok(Outcome::Created(invoice_id))
         }
-        Commands::ListClients => {
-            println!("Listing all clients...");
+        Commands::ListClients { format } => {
+            if format == OutputFormat::Table {
+                println!("Listing all clients...");
+            }
 
             // Get client list from database
             let clients = database::get_clients(connection)?;
-
-            // Notify if no clients are found
-            if clients.len() == 0 {
-                println!("No clients found");
-            } else {
-                // Else, list all clients
-                println!("===========");
-                for client in clients {
-                    println!(
-                        "id: {} \nname: {} \nnickname: {}\nemail: {}\nphone number: {}",
-                        client.id, client.name, client.nickname, client.email, client.phone_number
-                    );
-                    println!("===========");
-                }
-            }
-            Ok(())
+            output::write_clients(
+                &clients,
+                config.payment_terms_days,
+                format,
+                &mut io::stdout(),
+            )?;
+            Ok(Outcome::Printed)
         }
         Commands::DeleteClient { client_nickname } => {
             // If a nickname has been provided, change the message
@@ -108,48 +300,52 @@ pub fn execute_command(connection: &Connection, command: Commands) -> Result<()>
             database::delete_client(connection, &client_nickname)?;
             println!("Deleted client: {}", client_nickname);
 
-            Ok(())
+            Ok(Outcome::Deleted)
         }
-        Commands::ListInvoices { client_nickname } => {
-            let invoices;
+        Commands::ListInvoices {
+            client_nickname,
+            status,
+            format,
+        } => {
+            let invoices = database::get_invoices(connection, client_nickname.as_deref(), status)?;
 
-            // If a nickname has been provided, change the message and the query
-            match client_nickname {
-                Some(ref client_nickname) => {
-                    println!("Listing invoices for client: {}", client_nickname);
-                    invoices = database::get_invoices(connection, Some(client_nickname))?;
-                    if invoices.len() == 0 {
-                        println!("No invoices found for client: {}", &client_nickname);
+            // If a nickname has been provided, change the message
+            if format == OutputFormat::Table {
+                match client_nickname {
+                    Some(ref client_nickname) => {
+                        println!("Listing invoices for client: {}", client_nickname);
+                    }
+                    None => {
+                        println!("Listing all invoices...");
                     }
                 }
-                None => {
-                    println!("Listing all invoices...");
-                    invoices = database::get_invoices(connection, None)?;
-                    if invoices.len() == 0 {
-                        println!("No invoices found");
+                if invoices.is_empty() {
+                    match (client_nickname, status) {
+                        (Some(client_nickname), Some(status)) => {
+                            println!(
+                                "No {} invoices found for client: {}",
+                                status, client_nickname
+                            )
+                        }
+                        (Some(client_nickname), None) => {
+                            println!("No invoices found for client: {}", client_nickname)
+                        }
+                        (None, Some(status)) => println!("No {} invoices found", status),
+                        (None, None) => println!("No invoices found"),
                     }
                 }
             }
 
-            //  if invoices are found, print them all out
-            if invoices.len() != 0 {
-                println!("===========");
-                for invoice in invoices {
-                    println!(
-                        "id: {} \nclient id: {} \ndate: {}",
-                        invoice.id, invoice.client_id, invoice.date
-                    );
-                    for item in invoice.items {
-                        println!("\t++++++++");
-                        println!(
-                        "\titem id: {}\n\tdescription: {}\n\thours: {}\n\trate: {}\n\tamount: {}",
-                        item.id, item.description, item.hours, item.rate, item.amount
-                    );
-                    }
-                    println!("===========");
-                }
+            let mut listings = Vec::new();
+            for invoice in invoices {
+                let totals = tax::compute_totals(&invoice.items, &invoice.currency);
+                let payments = database::get_payments(connection, Some(invoice.id))?;
+                let credit_notes = database::get_credit_notes(connection, Some(invoice.id))?;
+                let balance = balance::compute_balance(&totals, &payments, &credit_notes);
+                listings.push(InvoiceListing { invoice, balance });
             }
-            Ok(())
+            output::write_invoices(&listings, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
         }
         Commands::DeleteInvoice { invoice_id } => {
             // If an invoice id has been provided, change the message
@@ -170,16 +366,178 @@ pub fn execute_command(connection: &Connection, command: Commands) -> Result<()>
             database::delete_invoice(connection, &invoice_id)?;
             println!("Deleted invoice with id: {}", invoice_id);
 
-            Ok(())
+            Ok(Outcome::Deleted)
+        }
+        Commands::Invoice {
+            action:
+                InvoiceCommands::AddItem {
+                    invoice_id,
+                    description,
+                    hours,
+                    rate,
+                    tax_code,
+                },
+        } => {
+            let invoice_id = parse_id(
+                invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: ")),
+                "invoice",
+            )?;
+            let currency = database::get_invoice_currency(connection, invoice_id)
+                .optional()?
+                .ok_or_else(|| Error::InvoiceNotFound(invoice_id.to_string()))?;
+
+            let description = description.unwrap_or_else(|| utils::prompt_for_str("Description: "));
+            let hours = hours.unwrap_or_else(|| utils::prompt_for_f64("Hours: "));
+            let rate = match rate {
+                Some(rate) => Money::parse(&rate, &currency)?,
+                None => utils::prompt_for_money("Rate: ", &currency),
+            };
+            items::check_item(&description, hours).map_err(Error::Validation)?;
+            let tax_code = tax_code.unwrap_or_else(|| tax::DEFAULT_TAX_CODE.to_string());
+            ensure_tax_code(connection, &tax_code)?;
+
+            let item_id = database::add_invoice_item(
+                connection,
+                invoice_id,
+                &description,
+                hours,
+                &rate,
+                &tax_code,
+            )?;
+            println!("Added item {} to invoice {}", item_id, invoice_id);
+
+            Ok(Outcome::Created(item_id))
+        }
+        Commands::Invoice {
+            action:
+                InvoiceCommands::EditItem {
+                    invoice_id,
+                    item_id,
+                    description,
+                    hours,
+                    rate,
+                    tax_code,
+                },
+        } => {
+            let invoice_id = parse_id(
+                invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: ")),
+                "invoice",
+            )?;
+            let item_id = parse_id(
+                item_id.unwrap_or_else(|| utils::prompt_for_str("Enter item ID: ")),
+                "item",
+            )?;
+            let invoice = database::get_invoice(connection, &invoice_id.to_string())?;
+            let item = invoice
+                .items
+                .iter()
+                .find(|item| item.id as i64 == item_id)
+                .ok_or_else(|| {
+                    Error::NotFound(format!(
+                        "invoice {} has no item with id {}",
+                        invoice_id, item_id
+                    ))
+                })?;
+
+            let interactive =
+                description.is_none() && hours.is_none() && rate.is_none() && tax_code.is_none();
+            let (description, hours, rate, tax_code) = if interactive {
+                let hours = loop {
+                    let hours = utils::prompt_with_default("Hours", &item.hours.to_string());
+                    match hours.parse::<f64>() {
+                        Ok(hours) => break hours,
+                        Err(e) => println!("Not a valid number: {}", e),
+                    }
+                };
+                let rate = loop {
+                    let rate = utils::prompt_with_default("Rate", &item.rate.to_string());
+                    match Money::parse(&rate, &invoice.currency) {
+                        Ok(rate) => break rate,
+                        Err(e) => println!("Not a valid amount: {}", e),
+                    }
+                };
+                (
+                    utils::prompt_with_default("Description", &item.description),
+                    hours,
+                    rate,
+                    utils::prompt_with_default("Tax code", &item.tax_rate.code),
+                )
+            } else {
+                // Only the given fields change
+                let rate = match rate {
+                    Some(rate) => Money::parse(&rate, &invoice.currency)?,
+                    None => item.rate.clone(),
+                };
+                (
+                    description.unwrap_or_else(|| item.description.clone()),
+                    hours.unwrap_or(item.hours),
+                    rate,
+                    tax_code.unwrap_or_else(|| item.tax_rate.code.clone()),
+                )
+            };
+            items::check_item(&description, hours).map_err(Error::Validation)?;
+            ensure_tax_code(connection, &tax_code)?;
+
+            database::update_invoice_item(
+                connection,
+                invoice_id,
+                item_id,
+                &description,
+                hours,
+                &rate,
+                &tax_code,
+            )?;
+            println!("Updated item {} on invoice {}", item_id, invoice_id);
+
+            Ok(Outcome::Saved)
+        }
+        Commands::Invoice {
+            action:
+                InvoiceCommands::RemoveItem {
+                    invoice_id,
+                    item_id,
+                },
+        } => {
+            let invoice_id = parse_id(
+                invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: ")),
+                "invoice",
+            )?;
+            let item_id = parse_id(
+                item_id.unwrap_or_else(|| utils::prompt_for_str("Enter item ID: ")),
+                "item",
+            )?;
+
+            database::remove_invoice_item(connection, invoice_id, item_id)?;
+            println!("Removed item {} from invoice {}", item_id, invoice_id);
+
+            Ok(Outcome::Deleted)
+        }
+        Commands::IssueInvoice { invoice_id } => {
+            transition_invoice(connection, invoice_id, InvoiceStatus::Issued)
+        }
+        Commands::MarkSent { invoice_id } => {
+            transition_invoice(connection, invoice_id, InvoiceStatus::Sent)
+        }
+        Commands::MarkPaid { invoice_id } => {
+            transition_invoice(connection, invoice_id, InvoiceStatus::Paid)
         }
-        Commands::Generate { invoice_id } => {
+        Commands::VoidInvoice { invoice_id } => {
+            transition_invoice(connection, invoice_id, InvoiceStatus::Void)
+        }
+        Commands::RecordPayment {
+            invoice_id,
+            amount,
+            date,
+            method,
+            reference,
+        } => {
             // If an invoice id has been provided, change the message
             match invoice_id {
                 Some(ref invoice_id) => {
-                    println!("Generating invoice pdf for invoice: {}...", invoice_id);
+                    println!("Recording payment for invoice: {}...", invoice_id);
                 }
                 None => {
-                    println!("Generating invoice pdf...");
+                    println!("Recording payment...");
                 }
             }
 
@@ -187,15 +545,794 @@ pub fn execute_command(connection: &Connection, command: Commands) -> Result<()>
             let invoice_id =
                 invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: "));
             let invoice = database::get_invoice(connection, &invoice_id)?;
+            if !matches!(invoice.status, InvoiceStatus::Issued | InvoiceStatus::Sent) {
+                return Err(Error::Validation(format!(
+                    "Cannot record a payment against a {} invoice",
+                    invoice.status
+                )));
+            }
+
+            // Values given as flags aren't prompted for again, so scripts fail
+            // instead of waiting for input
+            let amount = match amount {
+                Some(amount) => {
+                    parse_payment_amount(&amount, &invoice.currency).map_err(Error::Validation)?
+                }
+                None => loop {
+                    let amount = utils::prompt_for_str("Enter amount received: ");
+                    match parse_payment_amount(&amount, &invoice.currency) {
+                        Ok(amount) => break amount,
+                        Err(message) => println!("{}", message),
+                    }
+                },
+            };
+
+            // Payments default to today
+            let date = date.unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+            if NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
+                return Err(Error::Validation(format!(
+                    "Not a valid payment date, expected YYYY-MM-DD: {}",
+                    date
+                )));
+            }
+            let method = method.unwrap_or_else(|| "bank transfer".to_string());
+
+            // Record and notify
+            let payment_id = database::record_payment(
+                connection,
+                invoice.id,
+                &date,
+                &amount,
+                &method,
+                reference.as_deref(),
+            )?;
+            let totals = tax::compute_totals(&invoice.items, &invoice.currency);
+            let payments = database::get_payments(connection, Some(invoice.id))?;
+            let balance = balance::compute_balance(&totals, &payments, &invoice.credit_notes);
+            println!(
+                "Recorded payment of {} {} against invoice {}. Paid {} of {}, balance due {} {}",
+                amount,
+                invoice.currency,
+                invoice.id,
+                balance.amount_paid,
+                balance.total,
+                balance.balance_due,
+                invoice.currency
+            );
+
+            // Sent invoices that are now fully paid move to paid automatically
+            if balance.is_settled() && invoice.status == InvoiceStatus::Sent {
+                let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                database::set_invoice_status(
+                    connection,
+                    &invoice_id,
+                    InvoiceStatus::Paid,
+                    &timestamp,
+                )?;
+                println!("Invoice {} is now {}", invoice_id, InvoiceStatus::Paid);
+            }
+
+            Ok(Outcome::Created(payment_id))
+        }
+        Commands::ListPayments { invoice_id, format } => {
+            // If an invoice id has been provided, change the message and the query
+            let payments = match invoice_id {
+                Some(ref invoice_id) => {
+                    if format == OutputFormat::Table {
+                        println!("Listing payments for invoice: {}", invoice_id);
+                    }
+                    database::get_invoice(connection, invoice_id)?.payments
+                }
+                None => {
+                    if format == OutputFormat::Table {
+                        println!("Listing all payments...");
+                    }
+                    database::get_payments(connection, None)?
+                }
+            };
+
+            output::write_payments(&payments, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+        Commands::NewTaxRate { code, name, rate } => {
+            println!("Creating new tax rate...");
+
+            // Prompt for fields if not provided
+            let code = code.unwrap_or_else(|| utils::prompt_for_str("Enter tax code: "));
+            let name = name.unwrap_or_else(|| utils::prompt_for_str("Enter tax name: "));
+            let mut rate = rate.unwrap_or_else(|| utils::prompt_for_str("Enter rate (%): "));
+            let basis_points = loop {
+                match tax::parse_percent(&rate) {
+                    Some(basis_points) => break basis_points,
+                    None => {
+                        println!("Not a valid percentage");
+                        rate = utils::prompt_for_str("Enter rate (%): ");
+                    }
+                }
+            };
+
+            // Create and notify
+            database::new_tax_rate(connection, &code, &name, basis_points)?;
+            println!(
+                "Created tax rate: {} ({}) at {}",
+                name,
+                code,
+                tax::format_percent(basis_points)
+            );
+
+            Ok(Outcome::Saved)
+        }
+        Commands::ListTaxRates { format } => {
+            if format == OutputFormat::Table {
+                println!("Listing all tax rates...");
+            }
+
+            let rates = database::get_tax_rates(connection)?;
+            output::write_tax_rates(&rates, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+        Commands::Profile {
+            action: Some(ProfileCommands::List { format }),
+        } => {
+            if format == OutputFormat::Table {
+                println!("Listing all business profiles...");
+            }
+
+            let profiles = database::get_profiles(connection)?;
+            output::write_profiles(&profiles, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+        // `clinv profile` on its own shows the default profile
+        Commands::Profile { action: None } => execute_command(
+            connection,
+            config,
+            Commands::Profile {
+                action: Some(ProfileCommands::Show {
+                    name: None,
+                    format: OutputFormat::Table,
+                }),
+            },
+        ),
+        Commands::Profile {
+            action: Some(ProfileCommands::Show { name, format }),
+        } => {
+            match database::get_profile(connection, name.as_deref())? {
+                Some(profile) => output::write_profile(&profile, format, &mut io::stdout())?,
+                None => match name {
+                    Some(name) => return Err(profile_not_found(&name)),
+                    None if format == OutputFormat::Table => {
+                        println!("No business profile set up yet, run `clinv profile edit`")
+                    }
+                    None => {
+                        return Err(Error::NotFound(
+                            "no business profile set up yet; run `clinv profile edit`".to_string(),
+                        ))
+                    }
+                },
+            }
+            Ok(Outcome::Printed)
+        }
+        Commands::Profile {
+            action:
+                Some(ProfileCommands::Edit {
+                    name,
+                    business_name,
+                    abn,
+                    address,
+                    email,
+                    phone_number,
+                    bank_account_name,
+                    bsb,
+                    account_number,
+                    payid,
+                    logo,
+                }),
+        } => {
+            // Editing an unknown name creates a new profile
+            let current = match database::get_profile(connection, name.as_deref())? {
+                Some(profile) => profile,
+                None => BusinessProfile {
+                    name: name.unwrap_or_else(|| DEFAULT_PROFILE_NAME.to_string()),
+                    ..Default::default()
+                },
+            };
+            let interactive = [
+                &business_name,
+                &abn,
+                &address,
+                &email,
+                &phone_number,
+                &bank_account_name,
+                &bsb,
+                &account_number,
+                &payid,
+                &logo,
+            ]
+            .iter()
+            .all(|field| field.is_none());
+
+            let profile = if interactive {
+                prompt_for_profile(&current)
+            } else {
+                // Only the given fields change
+                BusinessProfile {
+                    business_name: business_name.unwrap_or(current.business_name),
+                    abn: abn.unwrap_or(current.abn),
+                    // Literal \n lets an address be given on one line
+                    address: address
+                        .map(|a| a.replace("\\n", "\n"))
+                        .unwrap_or(current.address),
+                    email: email.unwrap_or(current.email),
+                    phone_number: phone_number.unwrap_or(current.phone_number),
+                    bank_account_name: bank_account_name.unwrap_or(current.bank_account_name),
+                    bank_bsb: bsb.unwrap_or(current.bank_bsb),
+                    bank_account_number: account_number.unwrap_or(current.bank_account_number),
+                    payid: payid.unwrap_or(current.payid),
+                    logo_path: logo.unwrap_or(current.logo_path),
+                    ..current
+                }
+            };
+            validate_profile(&profile, &config.phone_region)?;
+
+            database::save_profile(connection, &profile)?;
+            println!(
+                "Saved business profile: {} ({})",
+                profile.name, profile.business_name
+            );
+
+            Ok(Outcome::Saved)
+        }
+        Commands::Profile {
+            action: Some(ProfileCommands::SetDefault { name }),
+        } => {
+            database::set_default_profile(connection, &name)?;
+            println!("New invoices will be issued by profile: {}", name);
+            Ok(Outcome::Saved)
+        }
+        Commands::CreditNote {
+            action:
+                CreditNoteCommands::New {
+                    invoice_id,
+                    full,
+                    reason,
+                },
+        } => {
+            println!("Creating new credit note...");
+
+            let invoice_id =
+                invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: "));
+            let invoice = database::get_invoice(connection, &invoice_id)?;
+            if !matches!(
+                invoice.status,
+                InvoiceStatus::Issued | InvoiceStatus::Sent | InvoiceStatus::Paid
+            ) {
+                return Err(Error::Validation(format!(
+                    "Cannot issue a credit note against a {} invoice",
+                    invoice.status
+                )));
+            }
+            let reason =
+                reason.unwrap_or_else(|| utils::prompt_for_str("Enter reason for the credit: "));
+
+            let items: Vec<NewItem> = if full {
+                invoice
+                    .items
+                    .iter()
+                    .map(|item| NewItem {
+                        description: item.description.clone(),
+                        hours: item.hours,
+                        rate: item.rate.clone(),
+                        tax_code: item.tax_rate.code.clone(),
+                    })
+                    .collect()
+            } else {
+                std::iter::from_fn(|| utils::prompt_for_item(connection, &invoice.currency))
+                    .collect()
+            };
+            if items.is_empty() {
+                return Err(Error::Validation(
+                    "A credit note needs at least one item".to_string(),
+                ));
+            }
+
+            // Credits can't add up to more than was invoiced
+            let totals = tax::compute_totals(&invoice.items, &invoice.currency);
+            let balance =
+                balance::compute_balance(&totals, &invoice.payments, &invoice.credit_notes);
+            let mut credit_items = Vec::new();
+            for item in &items {
+                let tax_rate =
+                    database::get_tax_rate(connection, &item.tax_code)?.ok_or_else(|| {
+                        Error::Validation(format!("Unknown tax code: {}", item.tax_code))
+                    })?;
+                credit_items.push(InvoiceItem {
+                    id: 0,
+                    description: item.description.clone(),
+                    hours: item.hours,
+                    rate: item.rate.clone(),
+                    amount: item.rate.times(item.hours),
+                    tax_rate,
+                });
+            }
+            let credit_total = tax::compute_totals(&credit_items, &invoice.currency).total;
+            let remaining = balance.total.clone() - balance.amount_credited;
+            if credit_total.minor_units > remaining.minor_units {
+                return Err(Error::Validation(format!(
+                    "Credit of {} {} is more than the {} {} left to credit on invoice {}",
+                    credit_total, invoice.currency, remaining, invoice.currency, invoice.id
+                )));
+            }
+
+            let date = Local::now().format("%Y-%m-%d").to_string();
+            let credit_note_id =
+                database::new_credit_note(connection, invoice.id, &date, &reason, &items)?;
+            let credit_note = database::get_credit_note(connection, &credit_note_id.to_string())?;
+            println!(
+                "Created credit note {} (id: {}) against invoice {}",
+                credit_note.number, credit_note_id, invoice.id
+            );
+
+            Ok(Outcome::Created(credit_note_id))
+        }
+        Commands::CreditNote {
+            action: CreditNoteCommands::List { invoice_id, format },
+        } => {
+            // If an invoice id has been provided, change the message and the query
+            let credit_notes = match invoice_id {
+                Some(ref invoice_id) => {
+                    if format == OutputFormat::Table {
+                        println!("Listing credit notes for invoice: {}", invoice_id);
+                    }
+                    database::get_invoice(connection, invoice_id)?.credit_notes
+                }
+                None => {
+                    if format == OutputFormat::Table {
+                        println!("Listing all credit notes...");
+                    }
+                    database::get_credit_notes(connection, None)?
+                }
+            };
+
+            let listings: Vec<CreditNoteListing> = credit_notes
+                .into_iter()
+                .map(|credit_note| {
+                    let total =
+                        tax::compute_totals(&credit_note.items, &credit_note.currency).total;
+                    CreditNoteListing { credit_note, total }
+                })
+                .collect();
+            output::write_credit_notes(&listings, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+        Commands::CreditNote {
+            action:
+                CreditNoteCommands::Generate {
+                    credit_note_id,
+                    output_format,
+                },
+        } => {
+            println!("Generating credit note {}...", output_format.as_str());
+
+            let credit_note_id =
+                credit_note_id.unwrap_or_else(|| utils::prompt_for_str("Enter credit note ID: "));
+            let credit_note = database::get_credit_note(connection, &credit_note_id)
+                .optional()?
+                .ok_or_else(|| {
+                    Error::NotFound(format!("no credit note with id {}", credit_note_id))
+                })?;
+            let invoice = database::get_invoice(connection, &credit_note.invoice_id.to_string())?;
+
+            let template = template::resolve(
+                &config.credit_note_template,
+                &config.template_dir,
+                DocumentType::CreditNote,
+            )?;
+            let (source, renderer) = document_source(output_format, config, &template)?;
+            check_template_used(&template, false, source, output_format)?;
+            let path = match renderer {
+                Some(renderer) => utils::generate_credit_note_pdf(
+                    &credit_note,
+                    &invoice,
+                    &template,
+                    &config.output_dir,
+                    renderer.as_ref(),
+                )?,
+                None => utils::generate_credit_note_document(
+                    &credit_note,
+                    &invoice,
+                    source,
+                    &template,
+                    &config.output_dir,
+                )?,
+            };
+            println!("Generated {} as {}", output_format.as_str(), path.display());
 
-            let result = generate_pdf(&invoice, "./template.html");
+            Ok(Outcome::Generated(path))
+        }
+        Commands::Numbering {
+            action:
+                NumberingCommands::Show {
+                    profile,
+                    document,
+                    format,
+                },
+        } => {
+            let profile_id = resolve_profile_id(connection, profile.as_deref())?;
+            let sequence = database::get_number_sequence(connection, profile_id, document)?;
+            let year = Local::now().year();
+            output::write_numbering(&sequence, year, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+        Commands::Numbering {
+            action:
+                NumberingCommands::Set {
+                    profile,
+                    document,
+                    prefix,
+                    padding,
+                    yearly_reset,
+                    next_number,
+                },
+        } => {
+            let profile_id = resolve_profile_id(connection, profile.as_deref())?;
+            let mut sequence = database::get_number_sequence(connection, profile_id, document)?;
+            if let Some(prefix) = prefix {
+                sequence.prefix = prefix;
+            }
+            if let Some(padding) = padding {
+                sequence.padding = padding;
+            }
+            if let Some(yearly_reset) = yearly_reset {
+                sequence.yearly_reset = yearly_reset;
+            }
+            if let Some(next_number) = next_number {
+                if next_number < 1 {
+                    return Err(Error::Validation(
+                        "Next number must be at least 1".to_string(),
+                    ));
+                }
+                // Applies to the current year even if it would otherwise restart
+                sequence.year = Some(Local::now().year());
+                sequence.next_number = next_number;
+            }
+
+            // Rewinding, or switching back to an old prefix, would hand out
+            // numbers that are already on issued documents
+            let year = Local::now().year();
+            let next = sequence.peek(year);
+            if let Some(highest) = database::highest_issued_number(connection, &sequence, year)? {
+                if next <= highest {
+                    return Err(Error::Validation(format!(
+                        "{} has already been issued; the next number must be at least {}",
+                        sequence.format(year, highest),
+                        highest + 1
+                    )));
+                }
+            }
 
-            match result {
-                Ok(result) => println!("Generated pdf as {}:", result),
-                Err(e) => println!("Could not generate pdf: {}", e),
+            database::save_number_sequence(connection, &sequence)?;
+            println!(
+                "The next {} issued will be numbered {}",
+                document,
+                sequence.format(year, next)
+            );
+            Ok(Outcome::Saved)
+        }
+        Commands::Report {
+            report: ReportCommands::Aging { format, as_of },
+        } => {
+            let as_of = match as_of {
+                Some(as_of) => NaiveDate::parse_from_str(&as_of, "%Y-%m-%d").map_err(|_| {
+                    Error::Validation(format!("Not a valid date, expected YYYY-MM-DD: {}", as_of))
+                })?,
+                None => Local::now().date_naive(),
+            };
+
+            let report = reports::aging_report(connection, as_of)?;
+            reports::write_aging_report(&report, format, &mut io::stdout())?;
+
+            Ok(Outcome::Printed)
+        }
+        Commands::Generate {
+            invoice_id,
+            output_format,
+            template,
+        } => {
+            let format_name = output_format.as_str();
+            // If an invoice id has been provided, change the message
+            match invoice_id {
+                Some(ref invoice_id) => {
+                    println!(
+                        "Generating invoice {} for invoice: {}...",
+                        format_name, invoice_id
+                    );
+                }
+                None => {
+                    println!("Generating invoice {}...", format_name);
+                }
+            }
+
+            // Prompt for invoice id if not provided
+            let invoice_id =
+                invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: "));
+            let invoice = database::get_invoice(connection, &invoice_id)?;
+            if invoice.issuer.business_name.is_empty() {
+                println!("Warning: no business profile set up, run `clinv profile edit`");
             }
 
-            Ok(())
+            // --template wins over the client's template, which wins over the
+            // configured default
+            let chosen = template.is_some();
+            let template = template
+                .or_else(|| invoice.client_template.clone())
+                .map_or_else(|| config.template.clone(), PathBuf::from);
+            let template =
+                template::resolve(&template, &config.template_dir, DocumentType::Invoice)?;
+
+            let (source, renderer) = document_source(output_format, config, &template)?;
+            check_template_used(&template, chosen, source, output_format)?;
+            let path = match renderer {
+                Some(renderer) => {
+                    generate_pdf(&invoice, &template, &config.output_dir, renderer.as_ref())?
+                }
+                None => utils::generate_invoice_document(
+                    &invoice,
+                    source,
+                    &template,
+                    &config.output_dir,
+                )?,
+            };
+            println!("Generated {} as {}", format_name, path.display());
+
+            Ok(Outcome::Generated(path))
+        }
+        Commands::Config { action } => execute_config_command(config, action),
+        Commands::Templates {
+            action: TemplateCommands::List { format },
+        } => {
+            output::write_templates(config, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+        Commands::Templates {
+            action:
+                TemplateCommands::Export {
+                    name,
+                    credit_note,
+                    force,
+                },
+        } => {
+            let document = if credit_note {
+                DocumentType::CreditNote
+            } else {
+                DocumentType::Invoice
+            };
+            let path = template::export(document, &name, &config.template_dir, force)?;
+            let key = if credit_note {
+                "credit_note_template"
+            } else {
+                "template"
+            };
+            println!(
+                "Exported the default {} template to {}",
+                document,
+                path.display()
+            );
+            println!("Use it with `clinv config set {} {}`", key, name);
+            Ok(Outcome::Generated(path))
+        }
+    }
+}
+
+/// Moves an invoice to `status`, prompting for the invoice id if needed
+fn transition_invoice(
+    connection: &Connection,
+    invoice_id: Option<String>,
+    status: InvoiceStatus,
+) -> Result<Outcome, Error> {
+    let invoice_id = invoice_id.unwrap_or_else(|| utils::prompt_for_str("Enter invoice ID: "));
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    database::set_invoice_status(connection, &invoice_id, status, &timestamp)?;
+    println!("Invoice {} is now {}", invoice_id, status);
+    if status == InvoiceStatus::Issued {
+        let invoice = database::get_invoice(connection, &invoice_id)?;
+        if let Some(number) = invoice.number {
+            println!("Numbered {}", number);
+        }
+    }
+
+    Ok(Outcome::Saved)
+}
+
+/// Parses an id typed by the user, naming `what` it identifies if it's not a
+/// number
+fn parse_id(id: String, what: &str) -> Result<i64, Error> {
+    id.trim()
+        .parse()
+        .map_err(|_| Error::Validation(format!("Not a valid {} ID: {}", what, id)))
+}
+
+fn ensure_tax_code(connection: &Connection, tax_code: &str) -> Result<(), Error> {
+    match database::get_tax_rate(connection, tax_code)? {
+        Some(_) => Ok(()),
+        None => Err(Error::Validation(format!("Unknown tax code: {}", tax_code))),
+    }
+}
+
+/// Parses payment terms given when editing a client, where `default` puts
+/// the client back on the default terms. The outer `None` means the terms
+/// aren't valid.
+fn parse_client_payment_terms(terms: &str) -> Option<Option<i64>> {
+    if terms.trim().eq_ignore_ascii_case("default") {
+        Some(None)
+    } else {
+        utils::parse_payment_terms(terms).map(Some)
+    }
+}
+
+/// Checks a client's template, where `default` puts the client back on the
+/// configured template
+fn parse_client_template(template: &str, config: &Config) -> Result<Option<String>, Error> {
+    if template.trim().eq_ignore_ascii_case("default") {
+        return Ok(None);
+    }
+    template::resolve(
+        Path::new(template),
+        &config.template_dir,
+        DocumentType::Invoice,
+    )?;
+    Ok(Some(template.to_string()))
+}
+
+/// Looks up the id of a named profile, or the default profile if no name is
+/// given. Invoices without any profile have no id.
+fn resolve_profile_id(connection: &Connection, name: Option<&str>) -> Result<Option<i32>, Error> {
+    match (database::get_profile(connection, name)?, name) {
+        (Some(profile), _) => Ok(Some(profile.id)),
+        (None, Some(name)) => Err(profile_not_found(name)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Runs a `config` command. These only touch the config file, so they don't
+/// need the database.
+pub fn execute_config_command(config: &Config, action: ConfigCommands) -> Result<Outcome, Error> {
+    match action {
+        ConfigCommands::Get { key } => {
+            println!("{}", config.get(&key)?);
+            Ok(Outcome::Printed)
+        }
+        ConfigCommands::Set { key, value } => {
+            let mut config = config.clone();
+            config.set(&key, &value)?;
+            config.save()?;
+            println!("Set {} to {}", key, config.get(&key)?);
+            Ok(Outcome::Saved)
+        }
+        ConfigCommands::List { format } => {
+            output::write_config(config, format, &mut io::stdout())?;
+            Ok(Outcome::Printed)
+        }
+    }
+}
+
+/// What a document in `format` is filled in from, and the renderer that
+/// turns it into a PDF. The PDF backend is only needed for PDFs.
+fn document_source(
+    format: DocumentFormat,
+    config: &Config,
+    template: &Template,
+) -> Result<(pdf::Source, Option<Box<dyn pdf::Renderer>>), Error> {
+    match format.source() {
+        Some(source) => Ok((source, None)),
+        None => {
+            let custom = matches!(template, Template::File(_));
+            let renderer = pdf::renderer(config.pdf_backend, custom)?;
+            Ok((renderer.source(), Some(renderer)))
         }
     }
 }
+
+/// Custom templates are HTML, so they're passed over when a document is laid
+/// out from the built-in text or Markdown, e.g. by the builtin PDF backend.
+/// That's an error for a template `chosen` with `--template`, and a warning
+/// for a configured one.
+fn check_template_used(
+    template: &Template,
+    chosen: bool,
+    source: pdf::Source,
+    format: DocumentFormat,
+) -> Result<(), Error> {
+    if source == pdf::Source::Html || !matches!(template, Template::File(_)) {
+        return Ok(());
+    }
+    let message = format!(
+        "template {} isn't used for {} output; custom templates need \
+        --output-format html or pdf_backend = \"wkhtmltopdf\"",
+        template.name(),
+        format.as_str()
+    );
+    if chosen {
+        return Err(Error::Usage(message));
+    }
+    eprintln!("Warning: {}", message);
+    Ok(())
+}
+
+/// A payment amount must be a valid amount of more than zero
+fn parse_payment_amount(amount: &str, currency: &str) -> Result<Money, String> {
+    match Money::parse(amount, currency) {
+        Ok(money) if money.minor_units > 0 => Ok(money),
+        Ok(_) => Err("Amount must be greater than zero".to_string()),
+        Err(e) => Err(format!("Not a valid amount: {}", e)),
+    }
+}
+
+fn profile_not_found(name: &str) -> Error {
+    database::ProfileError::ProfileNotFound(name.to_string()).into()
+}
+
+/// Interactively fills in a business profile, offering current values as defaults
+fn prompt_for_profile(current: &BusinessProfile) -> BusinessProfile {
+    let business_name = if current.business_name.is_empty() {
+        utils::prompt_for_str("Business name: ")
+    } else {
+        utils::prompt_with_default("Business name", &current.business_name)
+    };
+    let address = utils::prompt_with_default(
+        "Address (separate lines with \\n)",
+        &current.address.replace('\n', "\\n"),
+    )
+    .replace("\\n", "\n");
+    BusinessProfile {
+        name: current.name.clone(),
+        is_default: current.is_default,
+        id: current.id,
+        business_name,
+        abn: utils::prompt_with_default("ABN", &current.abn),
+        address,
+        email: utils::prompt_with_default("Email", &current.email),
+        phone_number: utils::prompt_with_default("Phone number", &current.phone_number),
+        bank_account_name: utils::prompt_with_default(
+            "Bank account name",
+            &current.bank_account_name,
+        ),
+        bank_bsb: utils::prompt_with_default("BSB", &current.bank_bsb),
+        bank_account_number: utils::prompt_with_default(
+            "Account number",
+            &current.bank_account_number,
+        ),
+        payid: utils::prompt_with_default("PayID", &current.payid),
+        logo_path: utils::prompt_with_default("Logo path", &current.logo_path),
+    }
+}
+
+/// Checks the fields that have a fixed format. Empty fields are allowed.
+fn validate_profile(profile: &BusinessProfile, phone_region: &str) -> Result<(), Error> {
+    if profile.business_name.trim().is_empty() {
+        return Err(Error::Validation(
+            "Business name must not be empty".to_string(),
+        ));
+    }
+    if !profile.abn.is_empty() && !utils::is_valid_abn(&profile.abn) {
+        return Err(Error::Validation(format!(
+            "Not a valid ABN: {}",
+            profile.abn
+        )));
+    }
+    if !profile.email.is_empty() && !utils::is_valid_email(&profile.email) {
+        return Err(Error::Validation(format!(
+            "Not a valid email: {}",
+            profile.email
+        )));
+    }
+    if !profile.phone_number.is_empty()
+        && !utils::is_valid_phone(&profile.phone_number, phone_region)
+    {
+        return Err(Error::Validation(format!(
+            "Not a valid phone number: {}",
+            profile.phone_number
+        )));
+    }
+    Ok(())
+}
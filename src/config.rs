@@ -0,0 +1,221 @@
+use crate::models::DEFAULT_PAYMENT_TERMS_DAYS;
+use crate::money::{self, DEFAULT_CURRENCY};
+use crate::pdf::PdfBackend;
+use crate::template;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Keys accepted by `clinv config get/set`, in the order they're listed
+pub const KEYS: &[&str] = &[
+    "template",
+    "credit_note_template",
+    "template_dir",
+    "output_dir",
+    "payment_terms_days",
+    "phone_region",
+    "currency",
+    "pdf_backend",
+];
+
+/// Settings read from `config.toml`. Anything left out of the file keeps its
+/// default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Invoice template for clients without their own: `default` for the
+    /// compiled-in one, the name of one in `template_dir`, or a path to an
+    /// HTML file
+    pub template: PathBuf,
+    pub credit_note_template: PathBuf,
+    /// Where named templates live, as `<name>.html`
+    pub template_dir: PathBuf,
+    /// Generated PDFs are saved in a folder per month under this directory
+    pub output_dir: PathBuf,
+    /// Terms for clients that don't have their own
+    pub payment_terms_days: i64,
+    /// Country phone numbers are checked against, e.g. AU
+    pub phone_region: String,
+    /// Currency of new invoices
+    pub currency: String,
+    pub pdf_backend: PdfBackend,
+    /// File the config was loaded from, and `set` writes to
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            template: PathBuf::from(template::DEFAULT_TEMPLATE),
+            credit_note_template: PathBuf::from(template::DEFAULT_TEMPLATE),
+            template_dir: PathBuf::from("templates"),
+            output_dir: PathBuf::from("invoices"),
+            payment_terms_days: DEFAULT_PAYMENT_TERMS_DAYS,
+            phone_region: "AU".to_string(),
+            currency: DEFAULT_CURRENCY.to_string(),
+            pdf_backend: PdfBackend::default(),
+            path: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    UnknownKey(String),
+    InvalidValue {
+        key: String,
+        message: String,
+    },
+    /// The file isn't valid TOML or has unknown settings
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+    /// There's nowhere to save changes
+    NoPath,
+    Io(io::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnknownKey(key) => write!(
+                f,
+                "unknown config key '{}', expected one of: {}",
+                key,
+                KEYS.join(", ")
+            ),
+            ConfigError::InvalidValue { key, message } => {
+                write!(f, "invalid value for {}: {}", key, message)
+            }
+            ConfigError::Parse { path, message } => {
+                write!(f, "couldn't read config {}: {}", path.display(), message)
+            }
+            ConfigError::NoPath => write!(f, "no config file location; pass --config"),
+            ConfigError::Io(e) => write!(f, "config file error: {}", e),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+/// Where the config lives when `--config` isn't given, e.g.
+/// `~/.config/clinv/config.toml` on Linux
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("clinv").join("config.toml"))
+}
+
+impl Config {
+    /// Reads the config at `path`, using the defaults if the file doesn't
+    /// exist yet
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let mut config = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let mut config: Config =
+                    toml::from_str(&contents).map_err(|e| ConfigError::Parse {
+                        path: path.to_path_buf(),
+                        message: e.to_string(),
+                    })?;
+                config.validate()?;
+                config
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(e.into()),
+        };
+        config.path = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Checks settings read from a file the same way `set` checks them
+    fn validate(&mut self) -> Result<(), ConfigError> {
+        for key in ["payment_terms_days", "phone_region", "currency"] {
+            let value = self.get(key)?;
+            self.set(key, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the config back to the file it was loaded from
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = self.path.as_ref().ok_or(ConfigError::NoPath)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(|e| ConfigError::Parse {
+            path: path.clone(),
+            message: e.to_string(),
+        })?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Result<String, ConfigError> {
+        match key {
+            "template" => Ok(self.template.display().to_string()),
+            "credit_note_template" => Ok(self.credit_note_template.display().to_string()),
+            "template_dir" => Ok(self.template_dir.display().to_string()),
+            "output_dir" => Ok(self.output_dir.display().to_string()),
+            "payment_terms_days" => Ok(self.payment_terms_days.to_string()),
+            "phone_region" => Ok(self.phone_region.clone()),
+            "currency" => Ok(self.currency.clone()),
+            "pdf_backend" => Ok(self.pdf_backend.to_string()),
+            _ => Err(ConfigError::UnknownKey(key.to_string())),
+        }
+    }
+
+    /// Changes one setting, checking the value the same way the rest of
+    /// clinv would
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = |message: &str| ConfigError::InvalidValue {
+            key: key.to_string(),
+            message: message.to_string(),
+        };
+        match key {
+            "template" => self.template = PathBuf::from(value),
+            "credit_note_template" => self.credit_note_template = PathBuf::from(value),
+            "template_dir" => self.template_dir = PathBuf::from(value),
+            "output_dir" => self.output_dir = PathBuf::from(value),
+            "payment_terms_days" => {
+                self.payment_terms_days = utils::parse_payment_terms(value)
+                    .ok_or_else(|| invalid("expected terms like net14, 30 or receipt"))?
+            }
+            "phone_region" => {
+                let region = value.trim().to_uppercase();
+                if !utils::is_valid_phone_region(&region) {
+                    return Err(invalid("expected a two letter country code, e.g. AU"));
+                }
+                self.phone_region = region;
+            }
+            "currency" => {
+                let currency = value.trim().to_uppercase();
+                if !money::is_supported_currency(&currency) {
+                    return Err(invalid(
+                        "expected a three letter currency code with cents, e.g. AUD",
+                    ));
+                }
+                self.currency = currency;
+            }
+            "pdf_backend" => self.pdf_backend = value.parse().map_err(|e: String| invalid(&e))?,
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+        Ok(())
+    }
+}
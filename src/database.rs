@@ -1,42 +1,186 @@
-use crate::models::{Client, Invoice, InvoiceForPdf, InvoiceItem};
-use rusqlite::{Connection, OptionalExtension, Result};
+use crate::error::Error as ClinvError;
+use crate::migrations::{self, MigrationError};
+use crate::models::{
+    BusinessProfile, Client, CreditNote, DocumentType, Invoice, InvoiceForPdf, InvoiceItem,
+    InvoiceStatus, NewItem, NumberSequence, Payment, TaxRate,
+};
+use crate::money::Money;
+use crate::utils;
+use chrono::Datelike;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-pub fn init_db(connection: &Connection) -> Result<()> {
-    connection.execute(
-        "CREATE TABLE IF NOT EXISTS client (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            nickname TEXT UNIQUE NOT NULL,
-            email TEXT NOT NULL,
-            phone_number TEXT NOT NULL
-        )",
-        [],
-    )?;
-    connection.execute(
-        "CREATE TABLE IF NOT EXISTS invoice (
-            id INTEGER PRIMARY KEY,
-            client_id INTEGER NOT NULL,
-            date TEXT NOT NULL,
-            FOREIGN KEY (client_id) REFERENCES client(id)
+/// Why an invoice or one of its items couldn't be read or changed
+#[derive(Debug)]
+pub enum InvoiceError {
+    InvoiceNotFound(String),
+    InvalidTransition {
+        from: InvoiceStatus,
+        to: InvoiceStatus,
+    },
+    /// Only draft invoices can be changed or deleted
+    NotDraft(InvoiceStatus),
+    ItemNotFound {
+        invoice_id: String,
+        item_id: String,
+    },
+    Sqlite(rusqlite::Error),
+}
 
-        )",
-        [],
-    )?;
+impl fmt::Display for InvoiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvoiceError::InvoiceNotFound(id) => write!(f, "no invoice with id {}", id),
+            InvoiceError::InvalidTransition { from, to } => {
+                write!(f, "cannot move a {} invoice to {}", from, to)
+            }
+            InvoiceError::NotDraft(status) => write!(
+                f,
+                "cannot change a {} invoice, only drafts; void it instead",
+                status
+            ),
+            InvoiceError::ItemNotFound {
+                invoice_id,
+                item_id,
+            } => write!(f, "invoice {} has no item with id {}", invoice_id, item_id),
+            InvoiceError::Sqlite(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
 
-    connection.execute(
-        "CREATE TABLE IF NOT EXISTS invoice_item (
-            id INTEGER PRIMARY KEY,
-            invoice_id INTEGER NOT NULL,
-            description TEXT NOT NULL,
-            hours FLOAT NOT NULL,
-            rate FLOAT NOT NULL,
-            amount FLOAT NOT NULL,
-            FOREIGN KEY (invoice_id) REFERENCES invoice(id)
-        )",
-        [],
-    )?;
+impl Error for InvoiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InvoiceError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
-    Ok(())
+impl From<rusqlite::Error> for InvoiceError {
+    fn from(e: rusqlite::Error) -> Self {
+        InvoiceError::Sqlite(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    ClientNotFound(String),
+    InvalidEmail(String),
+    InvalidPhone(String),
+    /// Another client already has the nickname
+    DuplicateNickname(String),
+    /// Clients with invoices can't be deleted without orphaning them
+    HasInvoices(String),
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::ClientNotFound(nickname) => write!(f, "no client with nickname {}", nickname),
+            ClientError::InvalidEmail(email) => write!(f, "not a valid email: {}", email),
+            ClientError::InvalidPhone(phone) => write!(f, "not a valid phone number: {}", phone),
+            ClientError::DuplicateNickname(nickname) => {
+                write!(f, "there is already a client with nickname {}", nickname)
+            }
+            ClientError::HasInvoices(nickname) => write!(
+                f,
+                "client {} has invoices and can't be deleted; edit the client instead",
+                nickname
+            ),
+            ClientError::Sqlite(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ClientError {
+    fn from(e: rusqlite::Error) -> Self {
+        ClientError::Sqlite(e)
+    }
+}
+
+/// The only unique column on clients is the nickname, so a unique constraint
+/// failure means the nickname is taken
+fn client_write_error(e: rusqlite::Error, nickname: &str) -> ClientError {
+    match e {
+        rusqlite::Error::SqliteFailure(ref error, _)
+            if error.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE =>
+        {
+            ClientError::DuplicateNickname(nickname.to_string())
+        }
+        e => ClientError::Sqlite(e),
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    ProfileNotFound(String),
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::ProfileNotFound(name) => {
+                write!(f, "no profile named {}; see `clinv profile list`", name)
+            }
+            ProfileError::Sqlite(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl Error for ProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfileError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ProfileError {
+    fn from(e: rusqlite::Error) -> Self {
+        ProfileError::Sqlite(e)
+    }
+}
+
+/// Opens the schema for use, running any pending migrations first
+pub fn init_db(connection: &Connection) -> Result<(), MigrationError> {
+    migrations::migrate(connection)
+}
+
+/// Where the database lives when no path is given, e.g.
+/// `~/.local/share/clinv/clinv.db` on Linux
+pub fn default_db_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("clinv").join("clinv.db"))
+}
+
+/// Opens the database at `path` and brings its schema up to date, creating
+/// the file and its directory if they don't exist yet. Also returns whether
+/// the database was newly created.
+pub fn open_db(path: &Path) -> Result<(Connection, bool), ClinvError> {
+    let created = !path.exists();
+    if created {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    let connection = Connection::open(path)?;
+    init_db(&connection)?;
+    Ok((connection, created))
 }
 
 pub fn new_client(
@@ -45,21 +189,84 @@ pub fn new_client(
     nickname: &str,
     email: &str,
     phone_number: &str,
-) -> Result<()> {
-    connection.execute(
-        "INSERT INTO client (name, nickname, email, phone_number) VALUES (?1, ?2, ?3, ?4)",
-        &[name, nickname, email, phone_number],
+    payment_terms_days: Option<i64>,
+) -> Result<i64, ClientError> {
+    connection
+        .execute(
+            "INSERT INTO client (name, nickname, email, phone_number, payment_terms_days) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![name, nickname, email, phone_number, payment_terms_days],
+        )
+        .map_err(|e| client_write_error(e, nickname))?;
+    Ok(connection.last_insert_rowid())
+}
+
+/// Updates every field of the client currently known as `client_nickname`,
+/// checking the email and phone number the same way new clients are checked
+#[allow(clippy::too_many_arguments)]
+pub fn update_client(
+    connection: &Connection,
+    client_nickname: &str,
+    name: &str,
+    nickname: &str,
+    email: &str,
+    phone_number: &str,
+    payment_terms_days: Option<i64>,
+    phone_region: &str,
+) -> Result<(), ClientError> {
+    if !utils::is_valid_email(email) {
+        return Err(ClientError::InvalidEmail(email.to_string()));
+    }
+    if !utils::is_valid_phone(phone_number, phone_region) {
+        return Err(ClientError::InvalidPhone(phone_number.to_string()));
+    }
+
+    let updated = connection
+        .execute(
+            "UPDATE client SET name = ?1, nickname = ?2, email = ?3, phone_number = ?4, payment_terms_days = ?5
+            WHERE nickname = ?6",
+            params![name, nickname, email, phone_number, payment_terms_days, client_nickname],
+        )
+        .map_err(|e| client_write_error(e, nickname))?;
+    if updated == 0 {
+        return Err(ClientError::ClientNotFound(client_nickname.to_string()));
+    }
+    Ok(())
+}
+
+/// Sets the template a client's invoices are generated with, or clears it
+/// with `None`
+pub fn set_client_template(connection: &Connection, client_nickname: &str, template: Option<&str>) -> Result<(), ClientError> {
+    let updated = connection.execute(
+        "UPDATE client SET template = ?1 WHERE nickname = ?2",
+        params![template, client_nickname],
     )?;
+    if updated == 0 {
+        return Err(ClientError::ClientNotFound(client_nickname.to_string()));
+    }
     Ok(())
 }
 
-pub fn delete_client(connection: &Connection, client_nickname: &str) -> Result<()> {
-    connection.execute("DELETE FROM client WHERE nickname = ?1", &[client_nickname])?;
+/// Deletes a client, refusing if any invoices still refer to them
+pub fn delete_client(connection: &Connection, client_nickname: &str) -> Result<(), ClientError> {
+    let invoice_count: i64 = connection.query_row(
+        "SELECT COUNT(*) FROM invoice
+        INNER JOIN client ON invoice.client_id = client.id
+        WHERE client.nickname = ?1",
+        [client_nickname],
+        |row| row.get(0),
+    )?;
+    if invoice_count > 0 {
+        return Err(ClientError::HasInvoices(client_nickname.to_string()));
+    }
+
+    connection.execute("DELETE FROM client WHERE nickname = ?1", [client_nickname])?;
     Ok(())
 }
 
 pub fn get_clients(connection: &Connection) -> Result<Vec<Client>> {
-    let mut statement = connection.prepare("SELECT * FROM client")?;
+    let mut statement = connection.prepare(
+        "SELECT id, name, nickname, email, phone_number, payment_terms_days, template FROM client",
+    )?;
 
     let client_iter = statement.query_map([], |row| {
         Ok(Client {
@@ -68,6 +275,8 @@ pub fn get_clients(connection: &Connection) -> Result<Vec<Client>> {
             nickname: row.get(2)?,
             email: row.get(3)?,
             phone_number: row.get(4)?,
+            payment_terms_days: row.get(5)?,
+            template: row.get(6)?,
         })
     })?;
 
@@ -76,68 +285,365 @@ pub fn get_clients(connection: &Connection) -> Result<Vec<Client>> {
     Ok(clients)
 }
 
-pub fn new_invoice(connection: &Connection, client_nickname: &str, date_string: &str) -> Result<i64> {
+pub fn get_client(connection: &Connection, client_nickname: &str) -> Result<Option<Client>> {
+    connection
+        .query_row(
+            "SELECT id, name, nickname, email, phone_number, payment_terms_days, template FROM client
+            WHERE nickname = ?1",
+            [client_nickname],
+            |row| {
+                Ok(Client {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    nickname: row.get(2)?,
+                    email: row.get(3)?,
+                    phone_number: row.get(4)?,
+                    payment_terms_days: row.get(5)?,
+                    template: row.get(6)?,
+                })
+            },
+        )
+        .optional()
+}
+
+/// Creates a draft invoice for a client, issued by the given business
+/// profile. Unless `due_date` is given, the invoice is due after the client's
+/// payment terms, or `default_terms_days` if the client has none.
+pub fn new_invoice(
+    connection: &Connection,
+    client_nickname: &str,
+    date_string: &str,
+    due_date: Option<&str>,
+    profile_id: Option<i32>,
+    default_terms_days: i64,
+    currency: &str,
+) -> Result<i64, ClientError> {
     // Check if client exists
-    let client_exists: Option<i32> = connection
+    let client: Option<(i32, Option<i64>)> = connection
         .query_row(
-            "SELECT id FROM client WHERE nickname = ?1",
-            &[client_nickname],
-            |row| row.get(0),
+            "SELECT id, payment_terms_days FROM client WHERE nickname = ?1",
+            [client_nickname],
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )
         .optional()?;
-    if client_exists.is_none() {
-        return Err(rusqlite::Error::QueryReturnedNoRows);
-    }
-    // Insert new invoice
+    let Some((client_id, payment_terms_days)) = client else {
+        return Err(ClientError::ClientNotFound(client_nickname.to_string()));
+    };
+    let payment_terms_days = payment_terms_days.unwrap_or(default_terms_days);
+
+    // Insert new invoice. It's numbered when issued.
     connection.execute(
-        "INSERT INTO invoice (client_id, date) VALUES (?1, ?2)",
-        &[&client_exists.unwrap().to_string(), date_string],
+        "INSERT INTO invoice (client_id, date, due_date, profile_id, currency)
+        VALUES (?1, ?2, COALESCE(?3, date(?2, '+' || ?4 || ' days')), ?5, ?6)",
+        params![client_id, date_string, due_date, payment_terms_days, profile_id, currency],
     )?;
     let invoice_id = connection.last_insert_rowid();
 
     Ok(invoice_id)
 }
 
-pub fn delete_invoice(connection: &Connection, invoice_id: &str) -> Result<()> {
+/// Adds a line item to a draft invoice, computing its amount from hours and
+/// rate. Returns the new item's id.
+pub fn add_invoice_item(
+    connection: &Connection,
+    invoice_id: i64,
+    description: &str,
+    hours: f64,
+    rate: &Money,
+    tax_code: &str,
+) -> Result<i64, InvoiceError> {
+    ensure_draft(connection, &invoice_id.to_string())?;
+    let amount = rate.times(hours);
+    connection.execute(
+        "INSERT INTO invoice_item (invoice_id, description, hours, rate_minor, amount_minor, tax_code) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![invoice_id, description, hours, rate.minor_units, amount.minor_units, tax_code],
+    )?;
+    Ok(connection.last_insert_rowid())
+}
+
+/// Replaces an item on a draft invoice, recomputing its amount
+pub fn update_invoice_item(
+    connection: &Connection,
+    invoice_id: i64,
+    item_id: i64,
+    description: &str,
+    hours: f64,
+    rate: &Money,
+    tax_code: &str,
+) -> Result<(), InvoiceError> {
+    ensure_draft(connection, &invoice_id.to_string())?;
+    let amount = rate.times(hours);
+    let updated = connection.execute(
+        "UPDATE invoice_item
+        SET description = ?1, hours = ?2, rate_minor = ?3, amount_minor = ?4, tax_code = ?5
+        WHERE id = ?6 AND invoice_id = ?7",
+        params![description, hours, rate.minor_units, amount.minor_units, tax_code, item_id, invoice_id],
+    )?;
+    if updated == 0 {
+        return Err(InvoiceError::ItemNotFound {
+            invoice_id: invoice_id.to_string(),
+            item_id: item_id.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Removes an item from a draft invoice
+pub fn remove_invoice_item(connection: &Connection, invoice_id: i64, item_id: i64) -> Result<(), InvoiceError> {
+    ensure_draft(connection, &invoice_id.to_string())?;
+    let removed = connection.execute(
+        "DELETE FROM invoice_item WHERE id = ?1 AND invoice_id = ?2",
+        params![item_id, invoice_id],
+    )?;
+    if removed == 0 {
+        return Err(InvoiceError::ItemNotFound {
+            invoice_id: invoice_id.to_string(),
+            item_id: item_id.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Fails unless the invoice exists and is still a draft
+fn ensure_draft(connection: &Connection, invoice_id: &str) -> Result<(), InvoiceError> {
+    let status: Option<InvoiceStatus> = connection
+        .query_row(
+            "SELECT status FROM invoice WHERE id = ?1",
+            [invoice_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match status {
+        None => Err(InvoiceError::InvoiceNotFound(invoice_id.to_string())),
+        Some(InvoiceStatus::Draft) => Ok(()),
+        Some(status) => Err(InvoiceError::NotDraft(status)),
+    }
+}
+
+/// Currency an invoice is billed in
+pub fn get_invoice_currency(connection: &Connection, invoice_id: i64) -> Result<String> {
+    connection.query_row(
+        "SELECT currency FROM invoice WHERE id = ?1",
+        [invoice_id],
+        |row| row.get(0),
+    )
+}
+
+/// Moves an invoice to a new lifecycle status, recording when it happened.
+/// Only the transitions allowed by `InvoiceStatus::can_transition_to` are
+/// accepted.
+///
+/// Issuing an invoice assigns it the next number in its profile's sequence,
+/// in the same transaction so that numbers are never skipped or reused.
+pub fn set_invoice_status(
+    connection: &Connection,
+    invoice_id: &str,
+    status: InvoiceStatus,
+    timestamp: &str,
+) -> Result<(), InvoiceError> {
+    let transaction = connection.unchecked_transaction()?;
+    let current: InvoiceStatus = transaction
+        .query_row(
+            "SELECT status FROM invoice WHERE id = ?1",
+            [invoice_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| InvoiceError::InvoiceNotFound(invoice_id.to_string()))?;
+
+    if !current.can_transition_to(status) {
+        return Err(InvoiceError::InvalidTransition {
+            from: current,
+            to: status,
+        });
+    }
+
+    // Column names can't be bound, but they come from a fixed set
+    let timestamp_column = match status {
+        InvoiceStatus::Draft => None,
+        InvoiceStatus::Issued => Some("issued_at"),
+        InvoiceStatus::Sent => Some("sent_at"),
+        InvoiceStatus::Paid => Some("paid_at"),
+        InvoiceStatus::Void => Some("voided_at"),
+    };
+    match timestamp_column {
+        Some(column) => transaction.execute(
+            &format!("UPDATE invoice SET status = ?1, {} = ?2 WHERE id = ?3", column),
+            params![status.as_str(), timestamp, invoice_id],
+        )?,
+        None => transaction.execute(
+            "UPDATE invoice SET status = ?1 WHERE id = ?2",
+            params![status.as_str(), invoice_id],
+        )?,
+    };
+    if status == InvoiceStatus::Issued {
+        assign_invoice_number(&transaction, invoice_id, timestamp)?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Gives an invoice the next number in its profile's sequence
+fn assign_invoice_number(connection: &Connection, invoice_id: &str, timestamp: &str) -> Result<()> {
+    let profile_id: Option<i32> = connection.query_row(
+        "SELECT profile_id FROM invoice WHERE id = ?1",
+        [invoice_id],
+        |row| row.get(0),
+    )?;
+    let (sequence_number, number) =
+        take_next_number(connection, profile_id, DocumentType::Invoice, timestamp)?;
     connection.execute(
-        "DELETE FROM invoice_item WHERE invoice_id = ?1",
-        &[invoice_id],
+        "UPDATE invoice SET sequence_number = ?1, number = ?2 WHERE id = ?3",
+        params![sequence_number, number, invoice_id],
     )?;
-    connection.execute("DELETE FROM invoice WHERE id = ?1", &[invoice_id])?;
     Ok(())
 }
 
-pub fn get_invoices(connection: &Connection, client_nickname: Option<&str>) -> Result<Vec<Invoice>> {
-    let mut statement;
-    let mut rows_iter;
-    match client_nickname {
-        Some(_) => {
-            statement = connection.prepare(
-                "SELECT
-                invoice.id as invoice_id, invoice.client_id, invoice.date,
-                invoice_item.id as item_id, invoice_item.description, invoice_item.hours,
-                invoice_item.rate, invoice_item.amount
-            FROM invoice
-            INNER JOIN client ON invoice.client_id = client.id
-            LEFT JOIN invoice_item on invoice.id = invoice_item.invoice_id
-            WHERE client.nickname = ?1
-            ORDER BY invoice.id",
-            )?;
-            rows_iter = statement.query([client_nickname])?;
-        }
-        None => {
-            statement = connection.prepare(
-                "SELECT
-                invoice.id as invoice_id, invoice.client_id, invoice.date,
-                invoice_item.id as item_id, invoice_item.description, invoice_item.hours,
-                invoice_item.rate, invoice_item.amount
-            FROM invoice
-            LEFT JOIN invoice_item on invoice.id = invoice_item.invoice_id
-            ORDER BY invoice.id",
-            )?;
-            rows_iter = statement.query([])?;
+/// Uses up the next number in a sequence, returning it along with its
+/// formatted form. Should be called inside the transaction that stores the
+/// number.
+fn take_next_number(
+    connection: &Connection,
+    profile_id: Option<i32>,
+    document_type: DocumentType,
+    timestamp: &str,
+) -> Result<(i64, String)> {
+    let mut sequence = get_number_sequence(connection, profile_id, document_type)?;
+
+    // Timestamps start with the date, e.g. 2026-01-31 09:00:00
+    let year = timestamp
+        .get(..4)
+        .and_then(|year| year.parse().ok())
+        .unwrap_or_else(|| chrono::Local::now().year());
+    let sequence_number = sequence.peek(year);
+    let number = sequence.format(year, sequence_number);
+
+    sequence.year = Some(year);
+    sequence.next_number = sequence_number + 1;
+    save_number_sequence(connection, &sequence)?;
+    Ok((sequence_number, number))
+}
+
+/// How a business profile numbers a type of document, or the default
+/// numbering if it hasn't been configured
+pub fn get_number_sequence(
+    connection: &Connection,
+    profile_id: Option<i32>,
+    document_type: DocumentType,
+) -> Result<NumberSequence> {
+    let sequence = connection
+        .query_row(
+            "SELECT prefix, padding, yearly_reset, year, next_number FROM number_sequence
+            WHERE profile_id IS ?1 AND document_type = ?2",
+            params![profile_id, document_type.as_str()],
+            |row| {
+                Ok(NumberSequence {
+                    profile_id,
+                    document_type,
+                    prefix: row.get(0)?,
+                    padding: row.get(1)?,
+                    yearly_reset: row.get(2)?,
+                    year: row.get(3)?,
+                    next_number: row.get(4)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(sequence.unwrap_or_else(|| NumberSequence::new(profile_id, document_type)))
+}
+
+/// The highest number `sequence` would give out again in `year`, among the
+/// documents its profile has already been issued. Numbering must carry on
+/// above it.
+pub fn highest_issued_number(
+    connection: &Connection,
+    sequence: &NumberSequence,
+    year: i32,
+) -> Result<Option<i64>> {
+    let sql = match sequence.document_type {
+        DocumentType::Invoice => {
+            "SELECT number FROM invoice WHERE profile_id IS ?1 AND number IS NOT NULL"
+        }
+        DocumentType::CreditNote => {
+            "SELECT number FROM credit_note WHERE profile_id IS ?1"
+        }
+    };
+    let mut statement = connection.prepare(sql)?;
+    let numbers = statement.query_map([sequence.profile_id], |row| row.get::<_, String>(0))?;
+    let mut highest = None;
+    for number in numbers {
+        if let Some(issued) = sequence.parse(year, &number?) {
+            highest = highest.max(Some(issued));
         }
     }
+    Ok(highest)
+}
+
+pub fn save_number_sequence(connection: &Connection, sequence: &NumberSequence) -> Result<()> {
+    // profile_id may be NULL, which a unique constraint can't match on
+    let values = params![
+        sequence.profile_id,
+        sequence.document_type.as_str(),
+        sequence.prefix,
+        sequence.padding,
+        sequence.yearly_reset,
+        sequence.year,
+        sequence.next_number,
+    ];
+    let updated = connection.execute(
+        "UPDATE number_sequence
+        SET prefix = ?3, padding = ?4, yearly_reset = ?5, year = ?6, next_number = ?7
+        WHERE profile_id IS ?1 AND document_type = ?2",
+        values,
+    )?;
+    if updated == 0 {
+        connection.execute(
+            "INSERT INTO number_sequence
+                (profile_id, document_type, prefix, padding, yearly_reset, year, next_number)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            values,
+        )?;
+    }
+    Ok(())
+}
+
+/// Deletes a draft invoice along with its items. Issued invoices have a
+/// number that must stay accounted for, so they can only be voided.
+pub fn delete_invoice(connection: &Connection, invoice_id: &str) -> Result<(), InvoiceError> {
+    ensure_draft(connection, invoice_id)?;
+
+    // Items, payments and the invoice go together or not at all
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute("DELETE FROM invoice_item WHERE invoice_id = ?1", [invoice_id])?;
+    transaction.execute("DELETE FROM payment WHERE invoice_id = ?1", [invoice_id])?;
+    transaction.execute("DELETE FROM invoice WHERE id = ?1", [invoice_id])?;
+    transaction.commit()?;
+    Ok(())
+}
+
+pub fn get_invoices(
+    connection: &Connection,
+    client_nickname: Option<&str>,
+    status: Option<InvoiceStatus>,
+) -> Result<Vec<Invoice>> {
+    // NULL filters match everything
+    let mut statement = connection.prepare(
+        "SELECT
+            invoice.id as invoice_id, invoice.client_id, invoice.date, invoice.currency,
+            invoice_item.id as item_id, invoice_item.description, invoice_item.hours,
+            invoice_item.rate_minor, invoice_item.amount_minor,
+            tax_rate.code, tax_rate.name, tax_rate.basis_points,
+            invoice.status, invoice.issued_at, invoice.sent_at, invoice.paid_at, invoice.voided_at,
+            invoice.due_date, invoice.profile_id, invoice.sequence_number, invoice.number
+        FROM invoice
+        LEFT JOIN client ON invoice.client_id = client.id
+        LEFT JOIN invoice_item on invoice.id = invoice_item.invoice_id
+        LEFT JOIN tax_rate on invoice_item.tax_code = tax_rate.code
+        WHERE (?1 IS NULL OR client.nickname = ?1)
+            AND (?2 IS NULL OR invoice.status = ?2)
+        ORDER BY invoice.id",
+    )?;
+    let mut rows_iter = statement.query(params![client_nickname, status.map(|s| s.as_str())])?;
 
     let mut invoices = Vec::new();
     let mut current_invoice_id = None;
@@ -153,18 +659,34 @@ pub fn get_invoices(connection: &Connection, client_nickname: Option<&str>) -> R
             current_invoice = Some(Invoice {
                 id: invoice_id,
                 client_id: row.get(1)?,
+                profile_id: row.get(18)?,
+                sequence_number: row.get(19)?,
+                number: row.get(20)?,
                 date: row.get(2)?,
+                due_date: row.get(17)?,
+                currency: row.get(3)?,
+                status: row.get(12)?,
+                issued_at: row.get(13)?,
+                sent_at: row.get(14)?,
+                paid_at: row.get(15)?,
+                voided_at: row.get(16)?,
                 items: Vec::new(),
             });
         }
         // If there's an item, add it
-        if let Some(item_id) = row.get::<_, Option<i32>>(3)? {
+        if let Some(item_id) = row.get::<_, Option<i32>>(4)? {
+            let currency: String = row.get(3)?;
             let item = InvoiceItem {
                 id: item_id,
-                description: row.get(4)?,
-                hours: row.get(5)?,
-                rate: row.get(6)?,
-                amount: row.get(7)?,
+                description: row.get(5)?,
+                hours: row.get(6)?,
+                rate: Money::new(row.get(7)?, &currency),
+                amount: Money::new(row.get(8)?, &currency),
+                tax_rate: TaxRate {
+                    code: row.get(9)?,
+                    name: row.get(10)?,
+                    basis_points: row.get(11)?,
+                },
             };
             if let Some(invoice) = current_invoice.as_mut() {
                 invoice.items.push(item);
@@ -178,16 +700,19 @@ pub fn get_invoices(connection: &Connection, client_nickname: Option<&str>) -> R
     Ok(invoices)
 }
 
-pub fn get_invoice(connection: &Connection, invoice_id: &str) -> Result<InvoiceForPdf> {
+pub fn get_invoice(connection: &Connection, invoice_id: &str) -> Result<InvoiceForPdf, InvoiceError> {
     let mut statement = connection.prepare(
         "SELECT 
             invoice.id as invoice_id, invoice.client_id, invoice.date,
             client.name, client.email, client.phone_number,
             invoice_item.id as item_id, invoice_item.description, invoice_item.hours,
-            invoice_item.rate, invoice_item.amount
+            invoice_item.rate_minor, invoice_item.amount_minor, invoice.currency,
+            tax_rate.code, tax_rate.name, tax_rate.basis_points, invoice.status,
+            invoice.due_date, invoice.profile_id, invoice.number, client.template
         FROM invoice
         RIGHT JOIN client on invoice.client_id = client.id
         LEFT JOIN invoice_item on invoice.id = invoice_item.invoice_id
+        LEFT JOIN tax_rate on invoice_item.tax_code = tax_rate.code
         WHERE invoice.id = ?1
         ",
     )?;
@@ -199,23 +724,40 @@ pub fn get_invoice(connection: &Connection, invoice_id: &str) -> Result<InvoiceF
     let mut client_email = None;
     let mut client_phone_number = None;
     let mut date = None;
+    let mut due_date = None;
+    let mut currency: Option<String> = None;
+    let mut status = None;
+    let mut profile_id: Option<i32> = None;
+    let mut number = None;
+    let mut client_template = None;
 
     while let Some(row) = rows_iter.next()? {
         if id.is_none() {
+            profile_id = row.get(17)?;
+            number = row.get(18)?;
+            client_template = row.get(19)?;
             id = Some(row.get(0)?);
             client_name = Some(row.get(3)?);
             client_email = Some(row.get(4)?);
             client_phone_number = Some(row.get(5)?);
             date = Some(row.get(2)?);
+            due_date = Some(row.get(16)?);
+            currency = Some(row.get(11)?);
+            status = Some(row.get(15)?);
         }
 
-        if let Some(item_id) = row.get::<_, Option<i32>>(6)? {
+        if let (Some(item_id), Some(currency)) = (row.get::<_, Option<i32>>(6)?, &currency) {
             items.push(InvoiceItem {
                 id: item_id,
                 description: row.get(7)?,
                 hours: row.get(8)?,
-                rate: row.get(9)?,
-                amount: row.get(10)?,
+                rate: Money::new(row.get(9)?, currency),
+                amount: Money::new(row.get(10)?, currency),
+                tax_rate: TaxRate {
+                    code: row.get(12)?,
+                    name: row.get(13)?,
+                    basis_points: row.get(14)?,
+                },
             });
         }
     }
@@ -226,18 +768,346 @@ pub fn get_invoice(connection: &Connection, invoice_id: &str) -> Result<InvoiceF
         Some(client_email),
         Some(client_phone_number),
         Some(date),
-    ) = (id, client_name, client_email, client_phone_number, date)
-    {
+        Some(due_date),
+        Some(currency),
+        Some(status),
+    ) = (
+        id,
+        client_name,
+        client_email,
+        client_phone_number,
+        date,
+        due_date,
+        currency,
+        status,
+    ) {
+        let payments = get_payments(connection, Some(id))?;
+        let credit_notes = get_credit_notes(connection, Some(id))?;
+        let issuer = match profile_id {
+            Some(profile_id) => get_profile_by_id(connection, profile_id)?,
+            None => None,
+        };
         Ok(InvoiceForPdf {
             id,
+            number,
             client_name,
             client_email,
             client_phone_number,
+            client_template,
             date,
+            due_date,
+            currency,
+            status,
             items,
+            payments,
+            issuer: issuer.unwrap_or_default(),
+            credit_notes,
         })
     } else {
-        // No invoice found
-        Err(rusqlite::Error::QueryReturnedNoRows)
+        Err(InvoiceError::InvoiceNotFound(invoice_id.to_string()))
+    }
+}
+
+/// Records money received against an invoice. Returns the new payment's id.
+pub fn record_payment(
+    connection: &Connection,
+    invoice_id: i32,
+    date: &str,
+    amount: &Money,
+    method: &str,
+    reference: Option<&str>,
+) -> Result<i64> {
+    connection.execute(
+        "INSERT INTO payment (invoice_id, date, amount_minor, method, reference) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![invoice_id, date, amount.minor_units, method, reference],
+    )?;
+    Ok(connection.last_insert_rowid())
+}
+
+/// Payments in the order they were received, optionally for a single invoice
+pub fn get_payments(connection: &Connection, invoice_id: Option<i32>) -> Result<Vec<Payment>> {
+    let mut statement = connection.prepare(
+        "SELECT payment.id, payment.invoice_id, payment.date, payment.amount_minor,
+            payment.method, payment.reference, invoice.currency
+        FROM payment
+        INNER JOIN invoice ON payment.invoice_id = invoice.id
+        WHERE ?1 IS NULL OR payment.invoice_id = ?1
+        ORDER BY payment.date, payment.id",
+    )?;
+    let payments = statement
+        .query_map([invoice_id], |row| {
+            let currency: String = row.get(6)?;
+            Ok(Payment {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                date: row.get(2)?,
+                amount: Money::new(row.get(3)?, &currency),
+                method: row.get(4)?,
+                reference: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<Payment>>>()?;
+    Ok(payments)
+}
+
+/// Issues a credit note against an invoice, numbered from its profile's
+/// credit note sequence. Returns the new credit note's id.
+pub fn new_credit_note(
+    connection: &Connection,
+    invoice_id: i32,
+    date: &str,
+    reason: &str,
+    items: &[NewItem],
+) -> Result<i64> {
+    let transaction = connection.unchecked_transaction()?;
+    let profile_id: Option<i32> = transaction.query_row(
+        "SELECT profile_id FROM invoice WHERE id = ?1",
+        [invoice_id],
+        |row| row.get(0),
+    )?;
+    let (sequence_number, number) =
+        take_next_number(&transaction, profile_id, DocumentType::CreditNote, date)?;
+    transaction.execute(
+        "INSERT INTO credit_note (invoice_id, profile_id, sequence_number, number, date, reason)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![invoice_id, profile_id, sequence_number, number, date, reason],
+    )?;
+    let credit_note_id = transaction.last_insert_rowid();
+
+    for item in items {
+        let amount = item.rate.times(item.hours);
+        transaction.execute(
+            "INSERT INTO credit_note_item (credit_note_id, description, hours, rate_minor, amount_minor, tax_code)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                credit_note_id,
+                item.description,
+                item.hours,
+                item.rate.minor_units,
+                amount.minor_units,
+                item.tax_code
+            ],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(credit_note_id)
+}
+
+/// Credit notes in the order they were issued, optionally for a single invoice
+pub fn get_credit_notes(connection: &Connection, invoice_id: Option<i32>) -> Result<Vec<CreditNote>> {
+    query_credit_notes(connection, invoice_id, None)
+}
+
+pub fn get_credit_note(connection: &Connection, credit_note_id: &str) -> Result<CreditNote> {
+    query_credit_notes(connection, None, Some(credit_note_id))?
+        .pop()
+        .ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+fn query_credit_notes(
+    connection: &Connection,
+    invoice_id: Option<i32>,
+    credit_note_id: Option<&str>,
+) -> Result<Vec<CreditNote>> {
+    let mut statement = connection.prepare(
+        "SELECT
+            credit_note.id, credit_note.invoice_id, credit_note.number, credit_note.date,
+            credit_note.reason, invoice.currency,
+            credit_note_item.id, credit_note_item.description, credit_note_item.hours,
+            credit_note_item.rate_minor, credit_note_item.amount_minor,
+            tax_rate.code, tax_rate.name, tax_rate.basis_points
+        FROM credit_note
+        INNER JOIN invoice ON credit_note.invoice_id = invoice.id
+        LEFT JOIN credit_note_item ON credit_note.id = credit_note_item.credit_note_id
+        LEFT JOIN tax_rate ON credit_note_item.tax_code = tax_rate.code
+        WHERE (?1 IS NULL OR credit_note.invoice_id = ?1)
+            AND (?2 IS NULL OR credit_note.id = ?2)
+        ORDER BY credit_note.id, credit_note_item.id",
+    )?;
+    let mut rows_iter = statement.query(params![invoice_id, credit_note_id])?;
+
+    let mut credit_notes: Vec<CreditNote> = Vec::new();
+    while let Some(row) = rows_iter.next()? {
+        let id: i32 = row.get(0)?;
+        if credit_notes.last().map(|credit_note| credit_note.id) != Some(id) {
+            credit_notes.push(CreditNote {
+                id,
+                invoice_id: row.get(1)?,
+                number: row.get(2)?,
+                date: row.get(3)?,
+                reason: row.get(4)?,
+                currency: row.get(5)?,
+                items: Vec::new(),
+            });
+        }
+        let credit_note = credit_notes.last_mut().unwrap();
+        if let Some(item_id) = row.get::<_, Option<i32>>(6)? {
+            credit_note.items.push(InvoiceItem {
+                id: item_id,
+                description: row.get(7)?,
+                hours: row.get(8)?,
+                rate: Money::new(row.get(9)?, &credit_note.currency),
+                amount: Money::new(row.get(10)?, &credit_note.currency),
+                tax_rate: TaxRate {
+                    code: row.get(11)?,
+                    name: row.get(12)?,
+                    basis_points: row.get(13)?,
+                },
+            });
+        }
+    }
+    Ok(credit_notes)
+}
+
+pub fn new_tax_rate(connection: &Connection, code: &str, name: &str, basis_points: i64) -> Result<()> {
+    connection.execute(
+        "INSERT INTO tax_rate (code, name, basis_points) VALUES (?1, ?2, ?3)",
+        params![code, name, basis_points],
+    )?;
+    Ok(())
+}
+
+pub fn get_tax_rates(connection: &Connection) -> Result<Vec<TaxRate>> {
+    let mut statement =
+        connection.prepare("SELECT code, name, basis_points FROM tax_rate ORDER BY code")?;
+    let rates = statement
+        .query_map([], |row| {
+            Ok(TaxRate {
+                code: row.get(0)?,
+                name: row.get(1)?,
+                basis_points: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<TaxRate>>>()?;
+    Ok(rates)
+}
+
+pub fn get_tax_rate(connection: &Connection, code: &str) -> Result<Option<TaxRate>> {
+    connection
+        .query_row(
+            "SELECT code, name, basis_points FROM tax_rate WHERE code = ?1",
+            [code],
+            |row| {
+                Ok(TaxRate {
+                    code: row.get(0)?,
+                    name: row.get(1)?,
+                    basis_points: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+}
+
+const PROFILE_COLUMNS: &str = "id, name, is_default, business_name, abn, address, email,
+    phone_number, bank_account_name, bank_bsb, bank_account_number, payid, logo_path";
+
+fn profile_from_row(row: &rusqlite::Row) -> Result<BusinessProfile> {
+    Ok(BusinessProfile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        is_default: row.get(2)?,
+        business_name: row.get(3)?,
+        abn: row.get(4)?,
+        address: row.get(5)?,
+        email: row.get(6)?,
+        phone_number: row.get(7)?,
+        bank_account_name: row.get(8)?,
+        bank_bsb: row.get(9)?,
+        bank_account_number: row.get(10)?,
+        payid: row.get(11)?,
+        logo_path: row.get(12)?,
+    })
+}
+
+/// Looks up a business profile by name, or the default profile if no name
+/// is given
+pub fn get_profile(connection: &Connection, name: Option<&str>) -> Result<Option<BusinessProfile>> {
+    connection
+        .query_row(
+            &format!(
+                "SELECT {} FROM business_profile
+                WHERE (?1 IS NULL AND is_default = 1) OR name = ?1",
+                PROFILE_COLUMNS
+            ),
+            [name],
+            profile_from_row,
+        )
+        .optional()
+}
+
+fn get_profile_by_id(connection: &Connection, id: i32) -> Result<Option<BusinessProfile>> {
+    connection
+        .query_row(
+            &format!("SELECT {} FROM business_profile WHERE id = ?1", PROFILE_COLUMNS),
+            [id],
+            profile_from_row,
+        )
+        .optional()
+}
+
+pub fn get_profiles(connection: &Connection) -> Result<Vec<BusinessProfile>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM business_profile ORDER BY name",
+        PROFILE_COLUMNS
+    ))?;
+    let profiles = statement
+        .query_map([], profile_from_row)?
+        .collect::<Result<Vec<BusinessProfile>>>()?;
+    Ok(profiles)
+}
+
+/// Creates a business profile, or updates the one with the same name. The
+/// first profile created becomes the default.
+pub fn save_profile(connection: &Connection, profile: &BusinessProfile) -> Result<()> {
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute(
+        "INSERT INTO business_profile (name, business_name, abn, address, email,
+            phone_number, bank_account_name, bank_bsb, bank_account_number, payid, logo_path)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ON CONFLICT (name) DO UPDATE SET
+            business_name = excluded.business_name,
+            abn = excluded.abn,
+            address = excluded.address,
+            email = excluded.email,
+            phone_number = excluded.phone_number,
+            bank_account_name = excluded.bank_account_name,
+            bank_bsb = excluded.bank_bsb,
+            bank_account_number = excluded.bank_account_number,
+            payid = excluded.payid,
+            logo_path = excluded.logo_path",
+        params![
+            profile.name,
+            profile.business_name,
+            profile.abn,
+            profile.address,
+            profile.email,
+            profile.phone_number,
+            profile.bank_account_name,
+            profile.bank_bsb,
+            profile.bank_account_number,
+            profile.payid,
+            profile.logo_path,
+        ],
+    )?;
+    transaction.execute(
+        "UPDATE business_profile SET is_default = 1
+        WHERE name = ?1 AND NOT EXISTS (SELECT 1 FROM business_profile WHERE is_default = 1)",
+        [&profile.name],
+    )?;
+    transaction.commit()
+}
+
+/// Makes the named profile the one used when none is given
+pub fn set_default_profile(connection: &Connection, name: &str) -> Result<(), ProfileError> {
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute("UPDATE business_profile SET is_default = 0", [])?;
+    let updated = transaction.execute(
+        "UPDATE business_profile SET is_default = 1 WHERE name = ?1",
+        [name],
+    )?;
+    if updated == 0 {
+        // Dropping the transaction rolls back, keeping the old default
+        return Err(ProfileError::ProfileNotFound(name.to_string()));
     }
+    Ok(transaction.commit()?)
 }
@@ -0,0 +1,169 @@
+use crate::config::ConfigError;
+use crate::database::{ClientError, InvoiceError, ProfileError};
+use crate::items::ItemError;
+use crate::migrations::MigrationError;
+use crate::money::MoneyError;
+use std::fmt;
+use std::io;
+
+/// Anything that can stop a command, grouped by what the user can do about
+/// it. Module-level errors like `InvoiceError` convert into this at the
+/// command boundary.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line couldn't be understood
+    Usage(String),
+    ClientNotFound(String),
+    InvoiceNotFound(String),
+    /// Some other record doesn't exist, e.g. a profile or credit note
+    NotFound(String),
+    /// Input that was understood but isn't allowed
+    Validation(String),
+    /// A template couldn't be read or filled in
+    Template(String),
+    /// The PDF backend failed to render or save a document
+    Pdf(String),
+    Storage(rusqlite::Error),
+    Migration(MigrationError),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{}; see `clinv --help`", message),
+            Error::ClientNotFound(nickname) => write!(
+                f,
+                "no client with nickname {}; see `clinv list-clients`",
+                nickname
+            ),
+            Error::InvoiceNotFound(id) => {
+                write!(f, "no invoice with id {}; see `clinv list-invoices`", id)
+            }
+            Error::NotFound(message) => write!(f, "{}", message),
+            Error::Validation(message) => write!(f, "{}", message),
+            Error::Template(message) => write!(f, "template error: {}", message),
+            Error::Pdf(message) => write!(f, "could not generate pdf: {}", message),
+            Error::Storage(e) => write!(f, "database error: {}", e),
+            Error::Migration(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Process exit codes, so scripts can tell failures apart. Keep in sync with
+/// the README.
+pub mod exit_code {
+    pub const SUCCESS: u8 = 0;
+    /// Matches the code clap uses for bad arguments
+    pub const USAGE: u8 = 2;
+    pub const NOT_FOUND: u8 = 3;
+    pub const VALIDATION: u8 = 4;
+    /// The template or PDF backend failed
+    pub const PDF: u8 = 5;
+    /// The database or a file couldn't be read or written
+    pub const STORAGE: u8 = 6;
+}
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Usage(_) => exit_code::USAGE,
+            Error::ClientNotFound(_) | Error::InvoiceNotFound(_) | Error::NotFound(_) => {
+                exit_code::NOT_FOUND
+            }
+            Error::Validation(_) => exit_code::VALIDATION,
+            Error::Template(_) | Error::Pdf(_) => exit_code::PDF,
+            Error::Storage(_) | Error::Migration(_) | Error::Io(_) => exit_code::STORAGE,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Storage(e) => Some(e),
+            Error::Migration(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Storage(e)
+    }
+}
+
+impl From<MigrationError> for Error {
+    fn from(e: MigrationError) -> Self {
+        Error::Migration(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<MoneyError> for Error {
+    fn from(e: MoneyError) -> Self {
+        Error::Validation(e.to_string())
+    }
+}
+
+impl From<InvoiceError> for Error {
+    fn from(e: InvoiceError) -> Self {
+        match e {
+            InvoiceError::InvoiceNotFound(id) => Error::InvoiceNotFound(id),
+            InvoiceError::ItemNotFound { .. } => Error::NotFound(e.to_string()),
+            InvoiceError::InvalidTransition { .. } | InvoiceError::NotDraft(_) => {
+                Error::Validation(e.to_string())
+            }
+            InvoiceError::Sqlite(e) => Error::Storage(e),
+        }
+    }
+}
+
+impl From<ClientError> for Error {
+    fn from(e: ClientError) -> Self {
+        match e {
+            ClientError::ClientNotFound(nickname) => Error::ClientNotFound(nickname),
+            ClientError::InvalidEmail(_)
+            | ClientError::InvalidPhone(_)
+            | ClientError::DuplicateNickname(_)
+            | ClientError::HasInvoices(_) => Error::Validation(e.to_string()),
+            ClientError::Sqlite(e) => Error::Storage(e),
+        }
+    }
+}
+
+impl From<ProfileError> for Error {
+    fn from(e: ProfileError) -> Self {
+        match e {
+            ProfileError::ProfileNotFound(_) => Error::NotFound(e.to_string()),
+            ProfileError::Sqlite(e) => Error::Storage(e),
+        }
+    }
+}
+
+impl From<ItemError> for Error {
+    fn from(e: ItemError) -> Self {
+        match e {
+            ItemError::Sqlite(e) => Error::Storage(e),
+            _ => Error::Validation(e.to_string()),
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::Io(e) => Error::Io(e),
+            ConfigError::NoPath => Error::Usage(e.to_string()),
+            _ => Error::Validation(e.to_string()),
+        }
+    }
+}
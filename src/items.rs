@@ -0,0 +1,203 @@
+use crate::database;
+use crate::models::NewItem;
+use crate::money::Money;
+use crate::tax::DEFAULT_TAX_CODE;
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Why items given on the command line or in a file couldn't be used.
+/// `item` counts from 1 in the order the items were given.
+#[derive(Debug)]
+pub enum ItemError {
+    Invalid { item: usize, message: String },
+    UnknownFormat(String),
+    Empty(String),
+    Read(String),
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for ItemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemError::Invalid { item, message } => write!(f, "item {}: {}", item, message),
+            ItemError::UnknownFormat(path) => write!(
+                f,
+                "can't tell the format of {}, expected a .json, .toml or .csv file",
+                path
+            ),
+            ItemError::Empty(path) => write!(f, "no items in {}", path),
+            ItemError::Read(message) => write!(f, "couldn't read items: {}", message),
+            ItemError::Sqlite(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl Error for ItemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ItemError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ItemError {
+    fn from(e: rusqlite::Error) -> Self {
+        ItemError::Sqlite(e)
+    }
+}
+
+/// One item as written in an items file
+#[derive(Debug, Deserialize)]
+struct ItemRecord {
+    description: String,
+    hours: f64,
+    rate: Rate,
+    #[serde(default)]
+    tax_code: Option<String>,
+}
+
+/// Rates may be written as `"120.00"` or `120`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Rate {
+    Text(String),
+    Number(f64),
+}
+
+/// TOML files list items as `[[item]]` tables
+#[derive(Debug, Deserialize)]
+struct TomlItems {
+    #[serde(default)]
+    item: Vec<ItemRecord>,
+}
+
+/// Parses an `--item` flag of the form `description;hours;rate`, optionally
+/// followed by `;tax_code`
+pub fn parse_item_spec(spec: &str, currency: &str, item: usize) -> Result<NewItem, ItemError> {
+    let invalid = |message: String| ItemError::Invalid { item, message };
+
+    let fields: Vec<&str> = spec.split(';').map(str::trim).collect();
+    let [description, hours, rate, rest @ ..] = fields.as_slice() else {
+        return Err(invalid(format!(
+            "expected \"description;hours;rate\", got \"{}\"",
+            spec
+        )));
+    };
+    let tax_code = match rest {
+        [] => None,
+        [tax_code] => Some(tax_code.to_string()),
+        _ => return Err(invalid(format!("too many fields in \"{}\"", spec))),
+    };
+    let hours = hours
+        .parse()
+        .map_err(|_| invalid(format!("not a valid number of hours: {}", hours)))?;
+
+    new_item(description, hours, rate, tax_code, currency, item)
+}
+
+/// Reads items from a JSON, TOML or CSV file, picked by its extension.
+///
+/// JSON files hold an array of items, TOML files `[[item]]` tables and CSV
+/// files a `description,hours,rate,tax_code` header row. `tax_code` may be
+/// left out.
+pub fn read_items_file(path: &Path, currency: &str) -> Result<Vec<NewItem>, ItemError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ItemError::Read(format!("{}: {}", path.display(), e)))?;
+
+    let records: Vec<ItemRecord> = match extension.as_deref() {
+        Some("json") => {
+            serde_json::from_str(&contents).map_err(|e| ItemError::Read(e.to_string()))?
+        }
+        Some("toml") => {
+            toml::from_str::<TomlItems>(&contents)
+                .map_err(|e| ItemError::Read(e.to_string()))?
+                .item
+        }
+        Some("csv") => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(contents.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|e| ItemError::Read(e.to_string()))?,
+        _ => return Err(ItemError::UnknownFormat(path.display().to_string())),
+    };
+    if records.is_empty() {
+        return Err(ItemError::Empty(path.display().to_string()));
+    }
+
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let rate = match record.rate {
+                Rate::Text(rate) => rate,
+                Rate::Number(rate) => rate.to_string(),
+            };
+            new_item(
+                &record.description,
+                record.hours,
+                &rate,
+                record.tax_code,
+                currency,
+                i + 1,
+            )
+        })
+        .collect()
+}
+
+/// Checks every item's tax code exists, so nothing is saved if any is wrong
+pub fn check_tax_codes(connection: &Connection, items: &[NewItem]) -> Result<(), ItemError> {
+    for (i, item) in items.iter().enumerate() {
+        if database::get_tax_rate(connection, &item.tax_code)?.is_none() {
+            return Err(ItemError::Invalid {
+                item: i + 1,
+                message: format!("unknown tax code: {}", item.tax_code),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks an item's description and hours, however the item was entered
+pub fn check_item(description: &str, hours: f64) -> Result<(), String> {
+    if description.is_empty() {
+        return Err("description is empty".to_string());
+    }
+    if !hours.is_finite() || hours <= 0.0 {
+        return Err(format!("hours must be more than zero, got {}", hours));
+    }
+    Ok(())
+}
+
+fn new_item(
+    description: &str,
+    hours: f64,
+    rate: &str,
+    tax_code: Option<String>,
+    currency: &str,
+    item: usize,
+) -> Result<NewItem, ItemError> {
+    let invalid = |message: String| ItemError::Invalid { item, message };
+
+    check_item(description, hours).map_err(invalid)?;
+    let rate = Money::parse(rate, currency)
+        .map_err(|e| invalid(format!("not a valid rate {}: {}", rate, e)))?;
+
+    Ok(NewItem {
+        description: description.to_string(),
+        hours,
+        rate,
+        tax_code: tax_code
+            .filter(|code| !code.is_empty())
+            .unwrap_or_else(|| DEFAULT_TAX_CODE.to_string()),
+    })
+}
@@ -1,5 +1,16 @@
+pub mod balance;
 pub mod cli;
 pub mod commands;
+pub mod config;
 pub mod database;
+pub mod error;
+pub mod items;
+pub mod migrations;
 pub mod models;
+pub mod money;
+pub mod output;
+pub mod pdf;
+pub mod reports;
+pub mod tax;
+pub mod template;
 pub mod utils;
@@ -1,12 +1,34 @@
 use clap::Parser;
-use clinv::cli::{map_command_words, Cli};
+use clinv::cli::{map_command_words, Cli, Commands};
+use clinv::error::Error;
+use clinv::config::{self, Config};
 use clinv::{commands, database};
-use rusqlite::Connection;
+use std::path::Path;
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<(), Error> {
     let cli = Cli::parse();
-    let connection = Connection::open("./clinv.db")?;
-    database::init_db(&connection)?;
+
+    // A missing config file just means the defaults are used
+    let config_path = match cli.config {
+        Some(path) => path,
+        None => config::default_config_path().ok_or_else(|| {
+            Error::Usage(
+                "couldn't find a config directory; pass --config or set CLINV_CONFIG".to_string(),
+            )
+        })?,
+    };
+    let config = Config::load(&config_path)?;
 
     // Determine the command to execute
     let command = match cli.command {
@@ -14,11 +36,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         None => match map_command_words(&cli.raw_command.words) {
             Some(cmd) => cmd,
             None => {
-                return Err(format!("Unknown command: {}", cli.raw_command.words.join(" ")).into());
+                return Err(Error::Usage(format!(
+                    "Unknown command: {}",
+                    cli.raw_command.words.join(" ")
+                )));
             }
         },
     };
 
-    commands::execute_command(&connection, command)?;
+    // Config commands work even without a usable database
+    if let Commands::Config { action } = command {
+        commands::execute_config_command(&config, action)?;
+        return Ok(());
+    }
+
+    // --db and CLINV_DB win over the default location
+    let db_path = match cli.db {
+        Some(path) => path,
+        None => database::default_db_path().ok_or_else(|| {
+            Error::Usage("couldn't find a data directory; pass --db or set CLINV_DB".to_string())
+        })?,
+    };
+    let (connection, created) = database::open_db(&db_path)?;
+    if created {
+        // Messages go to stderr so they don't end up in piped output
+        eprintln!("Created a new database at {}", db_path.display());
+        if Path::new("clinv.db").exists() && db_path != Path::new("clinv.db") {
+            eprintln!(
+                "Note: found ./clinv.db from an older version; use `--db clinv.db` or move it to {}",
+                db_path.display()
+            );
+        }
+    }
+
+    commands::execute_command(&connection, &config, command)?;
     Ok(())
 }
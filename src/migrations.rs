@@ -0,0 +1,290 @@
+use rusqlite::Connection;
+use std::error::Error;
+use std::fmt;
+
+/// A single schema upgrade step.
+///
+/// Migrations are applied in order, each inside its own transaction, and the
+/// database's `user_version` pragma is bumped to `version` once it succeeds.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every schema change, oldest first. Never edit a migration once it has
+/// shipped; add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial client, invoice and invoice_item tables",
+        // Uses IF NOT EXISTS so databases created before versioning existed
+        // (user_version 0, tables already present) upgrade cleanly.
+        sql: "CREATE TABLE IF NOT EXISTS client (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            nickname TEXT UNIQUE NOT NULL,
+            email TEXT NOT NULL,
+            phone_number TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS invoice (
+            id INTEGER PRIMARY KEY,
+            client_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            FOREIGN KEY (client_id) REFERENCES client(id)
+        );
+        CREATE TABLE IF NOT EXISTS invoice_item (
+            id INTEGER PRIMARY KEY,
+            invoice_id INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            hours FLOAT NOT NULL,
+            rate FLOAT NOT NULL,
+            amount FLOAT NOT NULL,
+            FOREIGN KEY (invoice_id) REFERENCES invoice(id)
+        );",
+    },
+    Migration {
+        version: 2,
+        description: "store money as integer minor units with a currency",
+        // SQLite can't change a column's type in place, so invoice_item is
+        // rebuilt. ROUND() rounds half away from zero, matching Money.
+        sql: "ALTER TABLE invoice ADD COLUMN currency TEXT NOT NULL DEFAULT 'AUD';
+        CREATE TABLE invoice_item_new (
+            id INTEGER PRIMARY KEY,
+            invoice_id INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            hours FLOAT NOT NULL,
+            rate_minor INTEGER NOT NULL,
+            amount_minor INTEGER NOT NULL,
+            FOREIGN KEY (invoice_id) REFERENCES invoice(id)
+        );
+        INSERT INTO invoice_item_new (id, invoice_id, description, hours, rate_minor, amount_minor)
+            SELECT id, invoice_id, description, hours,
+                CAST(ROUND(rate * 100) AS INTEGER),
+                CAST(ROUND(amount * 100) AS INTEGER)
+            FROM invoice_item;
+        DROP TABLE invoice_item;
+        ALTER TABLE invoice_item_new RENAME TO invoice_item;",
+    },
+    Migration {
+        version: 3,
+        description: "tax rates on invoice items",
+        // Items that predate tax support were billed without it
+        sql: "CREATE TABLE tax_rate (
+            code TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            basis_points INTEGER NOT NULL
+        );
+        INSERT INTO tax_rate (code, name, basis_points) VALUES
+            ('gst', 'GST', 1000),
+            ('gst-free', 'GST-free', 0),
+            ('input-taxed', 'Input-taxed', 0),
+            ('none', 'No tax', 0);
+        ALTER TABLE invoice_item ADD COLUMN tax_code TEXT NOT NULL DEFAULT 'none';",
+    },
+    Migration {
+        version: 4,
+        description: "invoice lifecycle status and transition timestamps",
+        // Invoices created before statuses existed were followed up outside
+        // clinv, so they're taken as paid rather than all showing as overdue
+        sql: "ALTER TABLE invoice ADD COLUMN status TEXT NOT NULL DEFAULT 'draft';
+        ALTER TABLE invoice ADD COLUMN issued_at TEXT;
+        ALTER TABLE invoice ADD COLUMN sent_at TEXT;
+        ALTER TABLE invoice ADD COLUMN paid_at TEXT;
+        ALTER TABLE invoice ADD COLUMN voided_at TEXT;
+        UPDATE invoice SET status = 'paid', issued_at = date, paid_at = date;",
+    },
+    Migration {
+        version: 5,
+        description: "payments received against invoices",
+        // Invoices already marked paid get one payment for their total, so
+        // their balance is settled. Their items are all untaxed.
+        sql: "CREATE TABLE payment (
+            id INTEGER PRIMARY KEY,
+            invoice_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            amount_minor INTEGER NOT NULL,
+            method TEXT NOT NULL,
+            reference TEXT,
+            FOREIGN KEY (invoice_id) REFERENCES invoice(id)
+        );
+        INSERT INTO payment (invoice_id, date, amount_minor, method, reference)
+            SELECT invoice.id, invoice.date, SUM(invoice_item.amount_minor), 'unknown',
+                'paid before clinv tracked payments'
+            FROM invoice INNER JOIN invoice_item ON invoice_item.invoice_id = invoice.id
+            WHERE invoice.status = 'paid'
+            GROUP BY invoice.id;",
+    },
+    Migration {
+        version: 6,
+        description: "store invoice due dates",
+        // Every invoice so far was rendered as Net 30
+        sql: "ALTER TABLE invoice ADD COLUMN due_date TEXT;
+        UPDATE invoice SET due_date = date(date, '+30 days');",
+    },
+    Migration {
+        version: 7,
+        description: "per-client payment terms",
+        // NULL means the client is on the default terms
+        sql: "ALTER TABLE client ADD COLUMN payment_terms_days INTEGER;",
+    },
+    Migration {
+        version: 8,
+        description: "business profile of the invoice issuer",
+        sql: "CREATE TABLE business_profile (
+            id INTEGER PRIMARY KEY,
+            business_name TEXT NOT NULL,
+            abn TEXT NOT NULL DEFAULT '',
+            address TEXT NOT NULL DEFAULT '',
+            email TEXT NOT NULL DEFAULT '',
+            phone_number TEXT NOT NULL DEFAULT '',
+            bank_account_name TEXT NOT NULL DEFAULT '',
+            bank_bsb TEXT NOT NULL DEFAULT '',
+            bank_account_number TEXT NOT NULL DEFAULT '',
+            payid TEXT NOT NULL DEFAULT '',
+            logo_path TEXT NOT NULL DEFAULT ''
+        );",
+    },
+    Migration {
+        version: 9,
+        description: "named business profiles linked to invoices",
+        // The single existing profile (if any) becomes the default, issues
+        // every existing invoice and keeps its number
+        sql: "ALTER TABLE business_profile ADD COLUMN name TEXT NOT NULL DEFAULT 'default';
+        ALTER TABLE business_profile ADD COLUMN is_default INTEGER NOT NULL DEFAULT 0;
+        UPDATE business_profile SET is_default = 1;
+        CREATE UNIQUE INDEX business_profile_name ON business_profile(name);
+        ALTER TABLE invoice ADD COLUMN profile_id INTEGER REFERENCES business_profile(id);
+        ALTER TABLE invoice ADD COLUMN sequence_number INTEGER;
+        UPDATE invoice SET
+            profile_id = (SELECT id FROM business_profile WHERE is_default = 1),
+            sequence_number = id;",
+    },
+    Migration {
+        version: 10,
+        description: "configurable invoice numbering assigned at issue",
+        // Issued invoices keep the number they were printed with. Drafts are
+        // numbered when they're issued. NULLs never match in a unique index,
+        // so invoices without a profile are indexed under 0.
+        sql: "CREATE TABLE number_sequence (
+            id INTEGER PRIMARY KEY,
+            profile_id INTEGER,
+            document_type TEXT NOT NULL DEFAULT 'invoice',
+            prefix TEXT NOT NULL,
+            padding INTEGER NOT NULL,
+            yearly_reset INTEGER NOT NULL,
+            year INTEGER,
+            next_number INTEGER NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES business_profile(id)
+        );
+        ALTER TABLE invoice ADD COLUMN number TEXT;
+        UPDATE invoice SET number = 'INV-' || date || '-' || sequence_number
+            WHERE status != 'draft';
+        UPDATE invoice SET sequence_number = NULL WHERE status = 'draft';
+        CREATE UNIQUE INDEX invoice_number ON invoice(COALESCE(profile_id, 0), number);",
+    },
+    Migration {
+        version: 11,
+        description: "credit notes against issued invoices",
+        // The issuing profile is copied from the credited invoice so numbers
+        // can be kept unique per profile, like invoice_number
+        sql: "CREATE TABLE credit_note (
+            id INTEGER PRIMARY KEY,
+            invoice_id INTEGER NOT NULL,
+            profile_id INTEGER,
+            sequence_number INTEGER NOT NULL,
+            number TEXT NOT NULL,
+            date TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            FOREIGN KEY (invoice_id) REFERENCES invoice(id),
+            FOREIGN KEY (profile_id) REFERENCES business_profile(id)
+        );
+        CREATE UNIQUE INDEX credit_note_number ON credit_note(COALESCE(profile_id, 0), number);
+        CREATE TABLE credit_note_item (
+            id INTEGER PRIMARY KEY,
+            credit_note_id INTEGER NOT NULL,
+            description TEXT NOT NULL,
+            hours FLOAT NOT NULL,
+            rate_minor INTEGER NOT NULL,
+            amount_minor INTEGER NOT NULL,
+            tax_code TEXT NOT NULL,
+            FOREIGN KEY (credit_note_id) REFERENCES credit_note(id)
+        );",
+    },
+    Migration {
+        version: 12,
+        description: "per-client invoice template",
+        // NULL means the configured default template
+        sql: "ALTER TABLE client ADD COLUMN template TEXT;",
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The database was written by a newer version of clinv
+    NewerSchema {
+        found: i64,
+        supported: i64,
+    },
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NewerSchema { found, supported } => write!(
+                f,
+                "database schema version {} is newer than this version of clinv supports ({}); please upgrade clinv",
+                found, supported
+            ),
+            MigrationError::Sqlite(e) => write!(f, "migration failed: {}", e),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::Sqlite(e) => Some(e),
+            MigrationError::NewerSchema { .. } => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+/// The schema version this binary expects
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+pub fn schema_version(connection: &Connection) -> rusqlite::Result<i64> {
+    connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the database up to the latest schema, applying any pending
+/// migrations in order. Refuses to touch databases newer than this binary.
+pub fn migrate(connection: &Connection) -> Result<(), MigrationError> {
+    let current = schema_version(connection)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(MigrationError::NewerSchema {
+            found: current,
+            supported: latest,
+        });
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let transaction = connection.unchecked_transaction()?;
+        transaction.execute_batch(migration.sql)?;
+        // PRAGMA doesn't accept bound parameters
+        transaction.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
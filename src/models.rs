@@ -1,35 +1,329 @@
+use crate::money::Money;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug)]
+/// Days from the invoice date until payment is due
+pub const DEFAULT_PAYMENT_TERMS_DAYS: i64 = 30;
+
+#[derive(Debug, Serialize)]
 pub struct Client {
     pub id: i32,
     pub name: String,
     pub nickname: String,
     pub email: String,
-    pub phone_number: String
+    pub phone_number: String,
+    /// Days after the invoice date that payment is due, or `None` to use
+    /// `DEFAULT_PAYMENT_TERMS_DAYS`. Zero means due on receipt.
+    pub payment_terms_days: Option<i64>,
+    /// Template for this client's invoices, a name or path, or `None` for
+    /// the configured default
+    pub template: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct InvoiceItem {
     pub id: i32,
     pub description: String,
     pub hours: f64,
-    pub rate: f64,
-    pub amount: f64,
+    pub rate: Money,
+    pub amount: Money,
+    pub tax_rate: TaxRate,
+}
+
+/// An item that hasn't been stored yet. Its amount is worked out from hours
+/// and rate when it's saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewItem {
+    pub description: String,
+    pub hours: f64,
+    pub rate: Money,
+    pub tax_code: String,
+}
+
+/// A named tax rate that can be applied to invoice items, e.g. GST at 10%
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaxRate {
+    pub code: String,
+    pub name: String,
+    /// Rate in basis points (hundredths of a percent), so 10% is 1000
+    pub basis_points: i64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Invoice {
     pub id: i32,
     pub client_id: i32,
+    pub profile_id: Option<i32>,
+    /// Position in the issuing profile's sequence, assigned when issued
+    pub sequence_number: Option<i64>,
+    /// Formatted invoice number, e.g. INV-2026-0042. Drafts have none.
+    pub number: Option<String>,
     pub date: String,
+    pub due_date: String,
+    pub currency: String,
+    pub status: InvoiceStatus,
+    pub issued_at: Option<String>,
+    pub sent_at: Option<String>,
+    pub paid_at: Option<String>,
+    pub voided_at: Option<String>,
     pub items: Vec<InvoiceItem>
 }
 
+/// Where an invoice is in its lifecycle.
+///
+/// Invoices move forward through draft -> issued -> sent -> paid, and can be
+/// voided at any point before they are paid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceStatus {
+    Draft,
+    Issued,
+    Sent,
+    Paid,
+    Void,
+}
+
+impl InvoiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Draft => "draft",
+            InvoiceStatus::Issued => "issued",
+            InvoiceStatus::Sent => "sent",
+            InvoiceStatus::Paid => "paid",
+            InvoiceStatus::Void => "void",
+        }
+    }
+
+    pub fn can_transition_to(&self, next: InvoiceStatus) -> bool {
+        use InvoiceStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Issued) | (Issued, Sent) | (Sent, Paid) | (Draft | Issued | Sent, Void)
+        )
+    }
+}
+
+impl fmt::Display for InvoiceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for InvoiceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "draft" => Ok(InvoiceStatus::Draft),
+            "issued" => Ok(InvoiceStatus::Issued),
+            "sent" => Ok(InvoiceStatus::Sent),
+            "paid" => Ok(InvoiceStatus::Paid),
+            "void" => Ok(InvoiceStatus::Void),
+            _ => Err(format!(
+                "unknown status '{}', expected one of: draft, issued, sent, paid, void",
+                s
+            )),
+        }
+    }
+}
+
+impl Serialize for InvoiceStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl FromSql for InvoiceStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+/// Name given to the first business profile when none is chosen
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// A business that sends invoices, shown on every PDF. Empty strings mean a
+/// field hasn't been filled in.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BusinessProfile {
+    pub id: i32,
+    /// Short name used to pick the profile, like a client's nickname
+    pub name: String,
+    /// Used for new invoices when no profile is given
+    pub is_default: bool,
+    pub business_name: String,
+    /// Australian Business Number
+    pub abn: String,
+    pub address: String,
+    pub email: String,
+    pub phone_number: String,
+    pub bank_account_name: String,
+    pub bank_bsb: String,
+    pub bank_account_number: String,
+    /// PayID (e.g. a phone number or email) for Osko payments
+    pub payid: String,
+    /// Path to an image file shown at the top of the invoice
+    pub logo_path: String,
+}
+
 pub struct InvoiceForPdf {
     pub id: i32,
+    pub number: Option<String>,
     pub client_name: String,
     pub client_email: String,
     pub client_phone_number: String,
+    /// The client's own template, if they have one
+    pub client_template: Option<String>,
     pub date: String,
-    pub items: Vec<InvoiceItem>
+    pub due_date: String,
+    pub currency: String,
+    pub status: InvoiceStatus,
+    pub items: Vec<InvoiceItem>,
+    pub payments: Vec<Payment>,
+    pub issuer: BusinessProfile,
+    pub credit_notes: Vec<CreditNote>,
+}
+
+/// A credit against an issued invoice, e.g. for overbilling. Its total
+/// (including tax) is taken off the invoice's balance.
+#[derive(Debug, Serialize)]
+pub struct CreditNote {
+    pub id: i32,
+    pub invoice_id: i32,
+    pub number: String,
+    pub date: String,
+    pub reason: String,
+    pub currency: String,
+    pub items: Vec<InvoiceItem>,
+}
+
+/// Kinds of document that are numbered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentType {
+    Invoice,
+    CreditNote,
+}
+
+impl DocumentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentType::Invoice => "invoice",
+            DocumentType::CreditNote => "credit_note",
+        }
+    }
+
+    fn default_prefix(&self) -> &'static str {
+        match self {
+            DocumentType::Invoice => "INV-",
+            DocumentType::CreditNote => "CN-",
+        }
+    }
+}
+
+impl fmt::Display for DocumentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str().replace('_', "-"))
+    }
+}
+
+impl Serialize for DocumentType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl FromStr for DocumentType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "invoice" => Ok(DocumentType::Invoice),
+            "credit-note" => Ok(DocumentType::CreditNote),
+            _ => Err(format!(
+                "unknown document type '{}', expected invoice or credit-note",
+                s
+            )),
+        }
+    }
+}
+
+/// Money received against an invoice. An invoice may be paid off in several
+/// instalments.
+#[derive(Debug, Clone, Serialize)]
+pub struct Payment {
+    pub id: i32,
+    pub invoice_id: i32,
+    pub date: String,
+    pub amount: Money,
+    pub method: String,
+    pub reference: Option<String>,
+}
+
+/// How a business profile numbers one type of document, e.g. `INV-2026-0042`.
+///
+/// Numbers are handed out in order when documents are issued, so an issued
+/// invoice is never renumbered and the sequence has no gaps.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NumberSequence {
+    pub profile_id: Option<i32>,
+    pub document_type: DocumentType,
+    pub prefix: String,
+    /// Minimum number of digits, padded with zeros
+    pub padding: usize,
+    /// Restart at 1 each calendar year, with the year in the number
+    pub yearly_reset: bool,
+    /// Year the last number was assigned in
+    pub year: Option<i32>,
+    pub next_number: i64,
+}
+
+impl NumberSequence {
+    /// The sequence used until a profile's numbering is configured
+    pub fn new(profile_id: Option<i32>, document_type: DocumentType) -> NumberSequence {
+        NumberSequence {
+            profile_id,
+            document_type,
+            prefix: document_type.default_prefix().to_string(),
+            padding: 4,
+            yearly_reset: true,
+            year: None,
+            next_number: 1,
+        }
+    }
+
+    pub fn format(&self, year: i32, number: i64) -> String {
+        if self.yearly_reset {
+            format!("{}{}-{:0width$}", self.prefix, year, number, width = self.padding)
+        } else {
+            format!("{}{:0width$}", self.prefix, number, width = self.padding)
+        }
+    }
+
+    /// The sequence number behind `number`, if this sequence could have
+    /// formatted it in `year`, e.g. 3 for `INV-2026-0003`
+    pub fn parse(&self, year: i32, number: &str) -> Option<i64> {
+        let prefix = if self.yearly_reset {
+            format!("{}{}-", self.prefix, year)
+        } else {
+            self.prefix.clone()
+        };
+        let digits = number.strip_prefix(&prefix)?;
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+
+    /// The number the next document issued in `year` will get
+    pub fn peek(&self, year: i32) -> i64 {
+        if self.yearly_reset && self.year.is_some_and(|last| last != year) {
+            1
+        } else {
+            self.next_number
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use serde::{Serialize, Serializer};
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, Sub};
+
+/// Currency used when nothing else has been specified
+pub const DEFAULT_CURRENCY: &str = "AUD";
+
+/// ISO 4217 currencies with zero or three decimal places, e.g. JPY and BHD.
+/// `Money` always has two, so these aren't supported.
+const OTHER_DECIMAL_CURRENCIES: &[&str] = &[
+    "BHD", "BIF", "CLF", "CLP", "DJF", "GNF", "IQD", "ISK", "JOD", "JPY", "KMF", "KRW", "KWD",
+    "LYD", "OMR", "PYG", "RWF", "TND", "UGX", "UYI", "UYW", "VND", "VUV", "XAF", "XOF", "XPF",
+];
+
+/// Checks `code` is a three letter currency code with two decimal places
+pub fn is_supported_currency(code: &str) -> bool {
+    code.len() == 3
+        && code.chars().all(|c| c.is_ascii_uppercase())
+        && !OTHER_DECIMAL_CURRENCIES.contains(&code)
+}
+
+/// A fixed-point amount of money, stored as a whole number of minor units
+/// (cents) alongside its ISO 4217 currency code.
+///
+/// All supported currencies have two decimal places; see
+/// `is_supported_currency`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    pub minor_units: i64,
+    pub currency: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoneyError {
+    Empty,
+    Invalid(String),
+    Overflow,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::Empty => write!(f, "amount must not be empty"),
+            MoneyError::Invalid(input) => write!(f, "'{}' is not a valid amount", input),
+            MoneyError::Overflow => write!(f, "amount is too large"),
+        }
+    }
+}
+
+impl Error for MoneyError {}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: &str) -> Money {
+        Money {
+            minor_units,
+            currency: currency.to_string(),
+        }
+    }
+
+    pub fn zero(currency: &str) -> Money {
+        Money::new(0, currency)
+    }
+
+    /// Parses a decimal amount such as `120`, `99.5`, `-12.34` or `$1,200.00`.
+    ///
+    /// Digits beyond the second decimal place are rounded half away from zero,
+    /// so `0.125` becomes `0.13` and `-0.125` becomes `-0.13`.
+    pub fn parse(input: &str, currency: &str) -> Result<Money, MoneyError> {
+        let trimmed = input.trim();
+        if trimmed.is_empty() {
+            return Err(MoneyError::Empty);
+        }
+        let invalid = || MoneyError::Invalid(input.to_string());
+
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let digits: String = unsigned
+            .strip_prefix('$')
+            .unwrap_or(unsigned)
+            .chars()
+            .filter(|c| *c != ',')
+            .collect();
+
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, fraction),
+            None => (digits.as_str(), ""),
+        };
+        if (whole.is_empty() && fraction.is_empty())
+            || !whole.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| MoneyError::Overflow)?
+        };
+        let mut cents: i64 = fraction
+            .chars()
+            .chain("00".chars())
+            .take(2)
+            .collect::<String>()
+            .parse()
+            .map_err(|_| invalid())?;
+        // Round on the first discarded digit
+        if fraction.chars().nth(2).is_some_and(|c| c >= '5') {
+            cents += 1;
+        }
+
+        let minor_units = whole
+            .checked_mul(100)
+            .and_then(|units| units.checked_add(cents))
+            .ok_or(MoneyError::Overflow)?;
+
+        Ok(Money::new(
+            if negative { -minor_units } else { minor_units },
+            currency,
+        ))
+    }
+
+    /// Multiplies by a quantity (e.g. hours worked), rounding the result to
+    /// the nearest minor unit with halves rounded away from zero.
+    ///
+    /// The quantity is treated as exact to six decimal places so that values
+    /// like `0.1` don't pick up binary floating-point error.
+    pub fn times(&self, quantity: f64) -> Money {
+        const SCALE: i128 = 1_000_000;
+        let scaled_quantity = (quantity * SCALE as f64).round() as i128;
+        let product = self.minor_units as i128 * scaled_quantity;
+        Money::new(round_div(product, SCALE) as i64, &self.currency)
+    }
+
+    /// Returns the given number of basis points (hundredths of a percent) of
+    /// this amount, rounded half away from zero. `1000` is 10%.
+    pub fn basis_points(&self, basis_points: i64) -> Money {
+        let product = self.minor_units as i128 * basis_points as i128;
+        Money::new(round_div(product, 10_000) as i64, &self.currency)
+    }
+}
+
+/// Integer division rounding half away from zero
+fn round_div(numerator: i128, denominator: i128) -> i128 {
+    let half = denominator / 2;
+    if numerator >= 0 {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    /// Panics if the currencies differ; mixing currencies on one invoice is
+    /// a programming error.
+    fn add(self, other: Money) -> Money {
+        assert_eq!(
+            self.currency, other.currency,
+            "cannot add amounts in different currencies"
+        );
+        Money::new(self.minor_units + other.minor_units, &self.currency)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    /// Panics if the currencies differ, as with addition
+    fn sub(self, other: Money) -> Money {
+        assert_eq!(
+            self.currency, other.currency,
+            "cannot subtract amounts in different currencies"
+        );
+        Money::new(self.minor_units - other.minor_units, &self.currency)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let units = self.minor_units.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, units / 100, units % 100)
+    }
+}
+
+/// Serialises as a decimal string (e.g. `"1234.50"`) so amounts survive JSON
+/// and CSV round trips exactly. The currency is reported separately.
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
@@ -0,0 +1,478 @@
+use crate::balance::Balance;
+use crate::cli::OutputFormat;
+use crate::config::{self, Config};
+use crate::error::Error;
+use crate::models::{
+    BusinessProfile, Client, CreditNote, Invoice, NumberSequence, Payment, TaxRate,
+};
+use crate::money::Money;
+use crate::tax;
+use crate::template;
+use crate::utils;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::Path;
+
+const SEPARATOR: &str = "===========";
+
+/// An invoice with the amounts worked out from its payments and credit notes
+#[derive(Debug, Serialize)]
+pub struct InvoiceListing {
+    #[serde(flatten)]
+    pub invoice: Invoice,
+    #[serde(flatten)]
+    pub balance: Balance,
+}
+
+/// A credit note with its total, including tax
+#[derive(Debug, Serialize)]
+pub struct CreditNoteListing {
+    #[serde(flatten)]
+    pub credit_note: CreditNote,
+    pub total: Money,
+}
+
+/// One CSV row per invoice. Items are only included in JSON.
+#[derive(Serialize)]
+struct InvoiceRow<'a> {
+    id: i32,
+    number: Option<&'a str>,
+    client_id: i32,
+    profile_id: Option<i32>,
+    date: &'a str,
+    due_date: &'a str,
+    currency: &'a str,
+    status: &'a str,
+    issued_at: Option<&'a str>,
+    sent_at: Option<&'a str>,
+    paid_at: Option<&'a str>,
+    voided_at: Option<&'a str>,
+    total: &'a Money,
+    amount_credited: &'a Money,
+    amount_paid: &'a Money,
+    balance_due: &'a Money,
+}
+
+#[derive(Serialize)]
+struct CreditNoteRow<'a> {
+    id: i32,
+    number: &'a str,
+    invoice_id: i32,
+    date: &'a str,
+    reason: &'a str,
+    currency: &'a str,
+    total: &'a Money,
+}
+
+/// A number sequence along with the next number it will hand out
+#[derive(Serialize)]
+struct NumberingRow<'a> {
+    #[serde(flatten)]
+    sequence: &'a NumberSequence,
+    next: String,
+}
+
+/// One setting from the config file
+#[derive(Serialize)]
+struct ConfigRow<'a> {
+    key: &'a str,
+    value: String,
+}
+
+/// One template `templates list` can pick from
+#[derive(Serialize)]
+struct TemplateRow {
+    name: String,
+    builtin: bool,
+    configured: bool,
+}
+
+/// Writes clients. Tables show `default_terms_days` for clients without
+/// their own payment terms.
+pub fn write_clients(
+    clients: &[Client],
+    default_terms_days: i64,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(clients, out),
+        OutputFormat::Csv => write_csv(clients, out),
+        OutputFormat::Table => {
+            if clients.is_empty() {
+                writeln!(out, "No clients found")?;
+                return Ok(());
+            }
+            writeln!(out, "{}", SEPARATOR)?;
+            for client in clients {
+                writeln!(
+                    out,
+                    "id: {} \nname: {} \nnickname: {}\nemail: {}\nphone number: {}\npayment terms: {}",
+                    client.id,
+                    client.name,
+                    client.nickname,
+                    client.email,
+                    client.phone_number,
+                    utils::format_payment_terms(
+                        client.payment_terms_days.unwrap_or(default_terms_days)
+                    )
+                )?;
+                writeln!(out, "{}", SEPARATOR)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes invoices with their balances. Tables and JSON include each
+/// invoice's items.
+pub fn write_invoices(
+    invoices: &[InvoiceListing],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(invoices, out),
+        OutputFormat::Csv => {
+            let rows: Vec<InvoiceRow> = invoices
+                .iter()
+                .map(|listing| {
+                    let invoice = &listing.invoice;
+                    InvoiceRow {
+                        id: invoice.id,
+                        number: invoice.number.as_deref(),
+                        client_id: invoice.client_id,
+                        profile_id: invoice.profile_id,
+                        date: &invoice.date,
+                        due_date: &invoice.due_date,
+                        currency: &invoice.currency,
+                        status: invoice.status.as_str(),
+                        issued_at: invoice.issued_at.as_deref(),
+                        sent_at: invoice.sent_at.as_deref(),
+                        paid_at: invoice.paid_at.as_deref(),
+                        voided_at: invoice.voided_at.as_deref(),
+                        total: &listing.balance.total,
+                        amount_credited: &listing.balance.amount_credited,
+                        amount_paid: &listing.balance.amount_paid,
+                        balance_due: &listing.balance.balance_due,
+                    }
+                })
+                .collect();
+            write_csv(&rows, out)
+        }
+        OutputFormat::Table => {
+            if invoices.is_empty() {
+                return Ok(());
+            }
+            writeln!(out, "{}", SEPARATOR)?;
+            for InvoiceListing { invoice, balance } in invoices {
+                writeln!(
+                    out,
+                    "id: {} \nnumber: {}\nclient id: {} \ndate: {}\nstatus: {}\ntotal: {}\ncredited: {}\npaid: {}\nbalance due: {}",
+                    invoice.id,
+                    invoice.number.as_deref().unwrap_or("-"),
+                    invoice.client_id,
+                    invoice.date,
+                    invoice.status,
+                    balance.total,
+                    balance.amount_credited,
+                    balance.amount_paid,
+                    balance.balance_due
+                )?;
+                for item in &invoice.items {
+                    writeln!(out, "\t++++++++")?;
+                    writeln!(
+                        out,
+                        "\titem id: {}\n\tdescription: {}\n\thours: {}\n\trate: {}\n\tamount: {}\n\ttax: {}",
+                        item.id, item.description, item.hours, item.rate, item.amount, item.tax_rate.name
+                    )?;
+                }
+                writeln!(out, "{}", SEPARATOR)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn write_payments(
+    payments: &[Payment],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(payments, out),
+        OutputFormat::Csv => write_csv(payments, out),
+        OutputFormat::Table => {
+            if payments.is_empty() {
+                writeln!(out, "No payments found")?;
+                return Ok(());
+            }
+            writeln!(out, "{}", SEPARATOR)?;
+            for payment in payments {
+                writeln!(
+                    out,
+                    "id: {}\ninvoice id: {}\ndate: {}\namount: {}\nmethod: {}\nreference: {}",
+                    payment.id,
+                    payment.invoice_id,
+                    payment.date,
+                    payment.amount,
+                    payment.method,
+                    payment.reference.as_deref().unwrap_or("-")
+                )?;
+                writeln!(out, "{}", SEPARATOR)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes tax rates. JSON and CSV give the rate in basis points.
+pub fn write_tax_rates(
+    rates: &[TaxRate],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(rates, out),
+        OutputFormat::Csv => write_csv(rates, out),
+        OutputFormat::Table => {
+            writeln!(out, "{}", SEPARATOR)?;
+            for rate in rates {
+                writeln!(
+                    out,
+                    "code: {}\nname: {}\nrate: {}",
+                    rate.code,
+                    rate.name,
+                    tax::format_percent(rate.basis_points)
+                )?;
+                writeln!(out, "{}", SEPARATOR)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes a summary of each profile as a table, or every field as JSON/CSV
+pub fn write_profiles(
+    profiles: &[BusinessProfile],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(profiles, out),
+        OutputFormat::Csv => write_csv(profiles, out),
+        OutputFormat::Table => {
+            writeln!(out, "{}", SEPARATOR)?;
+            for profile in profiles {
+                let default = if profile.is_default { " (default)" } else { "" };
+                writeln!(
+                    out,
+                    "name: {}{}\nbusiness name: {}\nabn: {}",
+                    profile.name, default, profile.business_name, profile.abn
+                )?;
+                writeln!(out, "{}", SEPARATOR)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes every field of one profile
+pub fn write_profile(
+    profile: &BusinessProfile,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(profile, out),
+        OutputFormat::Csv => write_csv(std::slice::from_ref(profile), out),
+        OutputFormat::Table => {
+            writeln!(out, "{}", SEPARATOR)?;
+            writeln!(out, "name: {}", profile.name)?;
+            writeln!(
+                out,
+                "business name: {}\nabn: {}\naddress: {}\nemail: {}\nphone number: {}",
+                profile.business_name,
+                profile.abn,
+                profile.address.replace('\n', ", "),
+                profile.email,
+                profile.phone_number
+            )?;
+            writeln!(
+                out,
+                "bank account name: {}\nbsb: {}\naccount number: {}\npayid: {}\nlogo: {}",
+                profile.bank_account_name,
+                profile.bank_bsb,
+                profile.bank_account_number,
+                profile.payid,
+                profile.logo_path
+            )?;
+            writeln!(out, "{}", SEPARATOR)?;
+            Ok(())
+        }
+    }
+}
+
+pub fn write_credit_notes(
+    credit_notes: &[CreditNoteListing],
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => write_json(credit_notes, out),
+        OutputFormat::Csv => {
+            let rows: Vec<CreditNoteRow> = credit_notes
+                .iter()
+                .map(|listing| CreditNoteRow {
+                    id: listing.credit_note.id,
+                    number: &listing.credit_note.number,
+                    invoice_id: listing.credit_note.invoice_id,
+                    date: &listing.credit_note.date,
+                    reason: &listing.credit_note.reason,
+                    currency: &listing.credit_note.currency,
+                    total: &listing.total,
+                })
+                .collect();
+            write_csv(&rows, out)
+        }
+        OutputFormat::Table => {
+            if credit_notes.is_empty() {
+                writeln!(out, "No credit notes found")?;
+                return Ok(());
+            }
+            writeln!(out, "{}", SEPARATOR)?;
+            for CreditNoteListing { credit_note, total } in credit_notes {
+                writeln!(
+                    out,
+                    "id: {}\nnumber: {}\ninvoice id: {}\ndate: {}\nreason: {}\ntotal: {}",
+                    credit_note.id,
+                    credit_note.number,
+                    credit_note.invoice_id,
+                    credit_note.date,
+                    credit_note.reason,
+                    total
+                )?;
+                writeln!(out, "{}", SEPARATOR)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes a number sequence's settings and the next number it will give a
+/// document issued in `year`
+pub fn write_numbering(
+    sequence: &NumberSequence,
+    year: i32,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let row = NumberingRow {
+        sequence,
+        next: sequence.format(year, sequence.peek(year)),
+    };
+    match format {
+        OutputFormat::Json => write_json(&row, out),
+        OutputFormat::Csv => {
+            // The csv crate can't flatten, so the columns are spelled out
+            let mut writer = csv::Writer::from_writer(out);
+            writer
+                .write_record(["document_type", "prefix", "padding", "yearly_reset", "next"])
+                .map_err(io::Error::from)?;
+            writer
+                .write_record([
+                    sequence.document_type.as_str(),
+                    &sequence.prefix,
+                    &sequence.padding.to_string(),
+                    &sequence.yearly_reset.to_string(),
+                    &row.next,
+                ])
+                .map_err(io::Error::from)?;
+            writer.flush()?;
+            Ok(())
+        }
+        OutputFormat::Table => {
+            writeln!(
+                out,
+                "prefix: {}\npadding: {}\nyearly reset: {}\nnext number: {}",
+                sequence.prefix, sequence.padding, sequence.yearly_reset, row.next
+            )?;
+            Ok(())
+        }
+    }
+}
+
+/// Writes every setting. Tables start with the file they were read from.
+pub fn write_config(
+    config: &Config,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let rows = config::KEYS
+        .iter()
+        .map(|key| {
+            Ok(ConfigRow {
+                key,
+                value: config.get(key)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    match format {
+        OutputFormat::Json => write_json(&rows, out),
+        OutputFormat::Csv => write_csv(&rows, out),
+        OutputFormat::Table => {
+            if let Some(ref path) = config.path {
+                writeln!(out, "# {}", path.display())?;
+            }
+            for row in rows {
+                writeln!(out, "{} = {}", row.key, row.value)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes the built-in template and the named templates in the template
+/// directory. Tables mark the configured invoice template with a `*`.
+pub fn write_templates(
+    config: &Config,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let row = |name: String, builtin: bool| TemplateRow {
+        configured: config.template == Path::new(&name),
+        name,
+        builtin,
+    };
+    let mut rows = vec![row(template::DEFAULT_TEMPLATE.to_string(), true)];
+    for name in template::list(&config.template_dir)? {
+        rows.push(row(name, false));
+    }
+    match format {
+        OutputFormat::Json => write_json(&rows, out),
+        OutputFormat::Csv => write_csv(&rows, out),
+        OutputFormat::Table => {
+            for row in rows {
+                let mark = if row.configured { "*" } else { " " };
+                let builtin = if row.builtin { " (built-in)" } else { "" };
+                writeln!(out, "{} {}{}", mark, row.name, builtin)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_json<T: Serialize + ?Sized>(value: &T, out: &mut impl Write) -> Result<(), Error> {
+    serde_json::to_writer_pretty(&mut *out, value).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_csv<T: Serialize>(rows: &[T], out: &mut impl Write) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(out);
+    for row in rows {
+        writer.serialize(row).map_err(io::Error::from)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
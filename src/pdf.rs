@@ -0,0 +1,246 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Which backend turns documents into PDFs, set with `pdf_backend` in the
+/// config. Each is only available if clinv was built with its cargo feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfBackend {
+    /// wkhtmltopdf for custom templates, which only it renders, and the
+    /// builtin backend otherwise
+    #[default]
+    Auto,
+    /// Pure Rust, laid out from the plain text template (`printpdf` feature)
+    Builtin,
+    /// Renders the HTML template exactly, but needs libwkhtmltox installed
+    /// (`wkhtmltopdf` feature)
+    Wkhtmltopdf,
+}
+
+impl PdfBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PdfBackend::Auto => "auto",
+            PdfBackend::Builtin => "builtin",
+            PdfBackend::Wkhtmltopdf => "wkhtmltopdf",
+        }
+    }
+}
+
+impl fmt::Display for PdfBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PdfBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(PdfBackend::Auto),
+            "builtin" => Ok(PdfBackend::Builtin),
+            "wkhtmltopdf" => Ok(PdfBackend::Wkhtmltopdf),
+            other => Err(format!(
+                "unknown pdf backend '{}', expected auto, builtin or wkhtmltopdf",
+                other
+            )),
+        }
+    }
+}
+
+/// The kind of template a document is filled in from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The configured HTML template
+    Html,
+    /// The built-in plain text layout
+    Text,
+    /// The built-in Markdown layout
+    Markdown,
+}
+
+impl Source {
+    /// File extension for a document saved as-is
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Source::Html => "html",
+            Source::Text => "txt",
+            Source::Markdown => "md",
+        }
+    }
+}
+
+/// Turns a filled-in template into a PDF
+pub trait Renderer {
+    /// Which template to fill in for this renderer
+    fn source(&self) -> Source;
+
+    /// Writes `document`, the filled-in template, as a PDF at `path`
+    fn render(&self, title: &str, document: &str, path: &Path) -> Result<(), Error>;
+}
+
+/// Picks the renderer for `backend`, failing if it wasn't compiled in.
+/// `custom_template` says whether the document has a custom template, which
+/// `auto` keeps on wkhtmltopdf.
+pub fn renderer(backend: PdfBackend, custom_template: bool) -> Result<Box<dyn Renderer>, Error> {
+    let chosen = match backend {
+        PdfBackend::Auto if custom_template || !cfg!(feature = "printpdf") => {
+            PdfBackend::Wkhtmltopdf
+        }
+        PdfBackend::Auto => PdfBackend::Builtin,
+        other => other,
+    };
+    match chosen {
+        #[cfg(feature = "printpdf")]
+        PdfBackend::Builtin => Ok(Box::new(builtin::Builtin)),
+        #[cfg(feature = "wkhtmltopdf")]
+        PdfBackend::Wkhtmltopdf => Ok(Box::new(wkhtml::Wkhtmltopdf)),
+        #[allow(unreachable_patterns)]
+        other => {
+            let feature = match other {
+                PdfBackend::Builtin => "printpdf",
+                _ => "wkhtmltopdf",
+            };
+            let reason = if backend == PdfBackend::Auto && custom_template {
+                ", which renders custom templates"
+            } else {
+                ""
+            };
+            Err(Error::Pdf(format!(
+                "clinv was built without the {} backend{}; rebuild with `--features {}` or set pdf_backend",
+                other, reason, feature
+            )))
+        }
+    }
+}
+
+#[cfg(feature = "wkhtmltopdf")]
+mod wkhtml {
+    use super::{Renderer, Source};
+    use crate::error::Error;
+    use std::path::Path;
+    use wkhtmltopdf::{Orientation, PdfApplication};
+
+    pub struct Wkhtmltopdf;
+
+    impl Renderer for Wkhtmltopdf {
+        fn source(&self) -> Source {
+            Source::Html
+        }
+
+        fn render(&self, title: &str, document: &str, path: &Path) -> Result<(), Error> {
+            let pdf_app = PdfApplication::new()
+                .map_err(|e| Error::Pdf(format!("couldn't start wkhtmltopdf: {}", e)))?;
+            let mut pdfout = pdf_app
+                .builder()
+                .orientation(Orientation::Portrait)
+                .title(title)
+                .build_from_html(document)
+                .map_err(|e| Error::Pdf(format!("couldn't render {}: {}", title, e)))?;
+
+            pdfout
+                .save(path)
+                .map_err(|e| Error::Pdf(format!("couldn't save {}: {}", path.display(), e)))?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "printpdf")]
+mod builtin {
+    use super::{Renderer, Source};
+    use crate::error::Error;
+    use printpdf::{Mm, PdfDocument};
+    use std::fs::File;
+    use std::io::BufWriter;
+    use std::path::Path;
+
+    // DejaVu Sans Mono covers far more than the standard PDF fonts, which
+    // only have Latin-1, and is as wide as Courier
+    const FONT: &[u8] = include_bytes!("fonts/DejaVuSansMono.ttf");
+
+    // A4 with 20mm margins, fitting 80 columns of 10pt monospace
+    const PAGE_WIDTH: f32 = 210.0;
+    const PAGE_HEIGHT: f32 = 297.0;
+    const MARGIN: f32 = 20.0;
+    const FONT_SIZE: f32 = 10.0;
+    const LINE_HEIGHT: f32 = 4.5;
+    const COLUMNS: usize = 80;
+
+    pub struct Builtin;
+
+    impl Renderer for Builtin {
+        fn source(&self) -> Source {
+            Source::Text
+        }
+
+        fn render(&self, title: &str, document: &str, path: &Path) -> Result<(), Error> {
+            let pdf_error = |e: printpdf::Error| Error::Pdf(e.to_string());
+            check_glyphs(title, document)?;
+
+            let (doc, page, layer) =
+                PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            let font = doc.add_external_font(FONT).map_err(pdf_error)?;
+
+            let mut layer = doc.get_page(page).get_layer(layer);
+            let mut y = PAGE_HEIGHT - MARGIN;
+            for line in wrap(document) {
+                if y < MARGIN {
+                    let (page, new_layer) =
+                        doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+                    layer = doc.get_page(page).get_layer(new_layer);
+                    y = PAGE_HEIGHT - MARGIN;
+                }
+                if !line.trim().is_empty() {
+                    layer.use_text(line, FONT_SIZE, Mm(MARGIN), Mm(y), &font);
+                }
+                y -= LINE_HEIGHT;
+            }
+
+            let file = File::create(path)
+                .map_err(|e| Error::Pdf(format!("couldn't save {}: {}", path.display(), e)))?;
+            doc.save(&mut BufWriter::new(file)).map_err(pdf_error)
+        }
+    }
+
+    /// Fails on characters the font has no glyph for, which would otherwise
+    /// be left out of the PDF
+    fn check_glyphs(title: &str, document: &str) -> Result<(), Error> {
+        let face = ttf_parser::Face::parse(FONT, 0)
+            .map_err(|e| Error::Pdf(format!("couldn't read the built-in font: {}", e)))?;
+        let mut missing = String::new();
+        for c in document.chars() {
+            if !c.is_control() && face.glyph_index(c).is_none() && !missing.contains(c) {
+                missing.push(c);
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Pdf(format!(
+            "the builtin backend can't show \"{}\" in {}; use pdf_backend = \"wkhtmltopdf\" \
+            or --output-format html",
+            missing, title
+        )))
+    }
+
+    /// Splits the document into lines that fit the page, breaking long ones
+    /// at the column limit
+    fn wrap(document: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        for line in document.lines() {
+            let chars: Vec<char> = line.trim_end().chars().collect();
+            if chars.is_empty() {
+                lines.push(String::new());
+            }
+            for chunk in chars.chunks(COLUMNS) {
+                lines.push(chunk.iter().collect());
+            }
+        }
+        lines
+    }
+}
@@ -0,0 +1,206 @@
+use crate::balance;
+use crate::cli::OutputFormat;
+use crate::database;
+use crate::error::Error;
+use crate::models::InvoiceStatus;
+use crate::money::Money;
+use crate::tax;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// How far past its due date an outstanding balance is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgingBucket {
+    Current,
+    Days1To30,
+    Days31To60,
+    Days61To90,
+    Over90,
+}
+
+impl AgingBucket {
+    /// Zero or negative days overdue (not yet due) counts as current
+    pub fn for_days_overdue(days: i64) -> AgingBucket {
+        match days {
+            i64::MIN..=0 => AgingBucket::Current,
+            1..=30 => AgingBucket::Days1To30,
+            31..=60 => AgingBucket::Days31To60,
+            61..=90 => AgingBucket::Days61To90,
+            _ => AgingBucket::Over90,
+        }
+    }
+}
+
+/// Outstanding balances for one client in one currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgingRow {
+    pub client: String,
+    pub client_name: String,
+    pub currency: String,
+    pub current: Money,
+    pub days_1_30: Money,
+    pub days_31_60: Money,
+    pub days_61_90: Money,
+    pub days_90_plus: Money,
+    pub total: Money,
+}
+
+impl AgingRow {
+    fn new(client: &str, client_name: &str, currency: &str) -> AgingRow {
+        AgingRow {
+            client: client.to_string(),
+            client_name: client_name.to_string(),
+            currency: currency.to_string(),
+            current: Money::zero(currency),
+            days_1_30: Money::zero(currency),
+            days_31_60: Money::zero(currency),
+            days_61_90: Money::zero(currency),
+            days_90_plus: Money::zero(currency),
+            total: Money::zero(currency),
+        }
+    }
+
+    fn add(&mut self, bucket: AgingBucket, amount: &Money) {
+        let column = match bucket {
+            AgingBucket::Current => &mut self.current,
+            AgingBucket::Days1To30 => &mut self.days_1_30,
+            AgingBucket::Days31To60 => &mut self.days_31_60,
+            AgingBucket::Days61To90 => &mut self.days_61_90,
+            AgingBucket::Over90 => &mut self.days_90_plus,
+        };
+        *column = column.clone() + amount.clone();
+        self.total = self.total.clone() + amount.clone();
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgingReport {
+    pub as_of: String,
+    pub rows: Vec<AgingRow>,
+}
+
+/// Buckets the outstanding balance of every issued or sent invoice by how
+/// many days past its due date it is on `as_of`. Rows are ordered by client
+/// nickname; clients with nothing outstanding are left out.
+pub fn aging_report(
+    connection: &Connection,
+    as_of: NaiveDate,
+) -> Result<AgingReport, Error> {
+    let clients = database::get_clients(connection)?;
+    let mut rows: Vec<AgingRow> = Vec::new();
+
+    for invoice in database::get_invoices(connection, None, None)? {
+        if !matches!(invoice.status, InvoiceStatus::Issued | InvoiceStatus::Sent) {
+            continue;
+        }
+        let totals = tax::compute_totals(&invoice.items, &invoice.currency);
+        let payments = database::get_payments(connection, Some(invoice.id))?;
+        let credit_notes = database::get_credit_notes(connection, Some(invoice.id))?;
+        let balance = balance::compute_balance(&totals, &payments, &credit_notes);
+        if balance.is_settled() {
+            continue;
+        }
+
+        let due_date =
+            NaiveDate::parse_from_str(&invoice.due_date, "%Y-%m-%d").map_err(|_| {
+                Error::Validation(format!(
+                    "invoice {} has an invalid due date: {}",
+                    invoice.id, invoice.due_date
+                ))
+            })?;
+        let bucket = AgingBucket::for_days_overdue((as_of - due_date).num_days());
+
+        let client = clients.iter().find(|client| client.id == invoice.client_id);
+        let (nickname, name) = match client {
+            Some(client) => (client.nickname.as_str(), client.name.as_str()),
+            None => ("", "(deleted client)"),
+        };
+        let row = match rows
+            .iter_mut()
+            .position(|row| row.client == nickname && row.currency == invoice.currency)
+        {
+            Some(index) => &mut rows[index],
+            None => {
+                rows.push(AgingRow::new(nickname, name, &invoice.currency));
+                rows.last_mut().unwrap()
+            }
+        };
+        row.add(bucket, &balance.balance_due);
+    }
+
+    rows.sort_by(|a, b| (&a.client, &a.currency).cmp(&(&b.client, &b.currency)));
+    Ok(AgingReport {
+        as_of: as_of.format("%Y-%m-%d").to_string(),
+        rows,
+    })
+}
+
+/// Writes the report as an aligned table with a total per currency, or as
+/// CSV/JSON for other tools
+pub fn write_aging_report(
+    report: &AgingReport,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, report).map_err(io::Error::from)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in &report.rows {
+                writer.serialize(row).map_err(io::Error::from)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Table => {
+            writeln!(out, "Accounts receivable aging as of {}", report.as_of)?;
+            if report.rows.is_empty() {
+                writeln!(out, "No outstanding balances")?;
+                return Ok(());
+            }
+
+            let mut totals: Vec<AgingRow> = Vec::new();
+            for row in &report.rows {
+                let total = match totals.iter().position(|t| t.currency == row.currency) {
+                    Some(index) => &mut totals[index],
+                    None => {
+                        totals.push(AgingRow::new("TOTAL", "", &row.currency));
+                        totals.last_mut().unwrap()
+                    }
+                };
+                total.add(AgingBucket::Current, &row.current);
+                total.add(AgingBucket::Days1To30, &row.days_1_30);
+                total.add(AgingBucket::Days31To60, &row.days_31_60);
+                total.add(AgingBucket::Days61To90, &row.days_61_90);
+                total.add(AgingBucket::Over90, &row.days_90_plus);
+            }
+
+            let line = |client: &str, cells: [String; 7]| {
+                format!(
+                    "{:<16} {:>4} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+                    client, cells[0], cells[1], cells[2], cells[3], cells[4], cells[5], cells[6]
+                )
+            };
+            let header =
+                ["CCY", "Current", "1-30", "31-60", "61-90", "90+", "Total"].map(String::from);
+            writeln!(out, "{}", line("Client", header))?;
+            for row in report.rows.iter().chain(totals.iter()) {
+                let cells = [
+                    row.currency.clone(),
+                    row.current.to_string(),
+                    row.days_1_30.to_string(),
+                    row.days_31_60.to_string(),
+                    row.days_61_90.to_string(),
+                    row.days_90_plus.to_string(),
+                    row.total.to_string(),
+                ];
+                writeln!(out, "{}", line(&row.client, cells))?;
+            }
+        }
+    }
+    Ok(())
+}
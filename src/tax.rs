@@ -0,0 +1,82 @@
+use crate::models::{InvoiceItem, TaxRate};
+use crate::money::Money;
+use serde::Serialize;
+
+/// Tax code applied to new items unless another is chosen
+pub const DEFAULT_TAX_CODE: &str = "gst";
+
+/// Tax charged at a single rate across an invoice
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaxLine {
+    pub rate: TaxRate,
+    /// Sum of the (tax-exclusive) item amounts at this rate
+    pub taxable: Money,
+    pub tax: Money,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceTotals {
+    pub subtotal: Money,
+    pub tax_lines: Vec<TaxLine>,
+    pub tax_total: Money,
+    pub total: Money,
+}
+
+/// Computes the subtotal, tax per rate and grand total for a set of items.
+///
+/// Item amounts are tax-exclusive. Tax is calculated once per rate on the sum
+/// of the items at that rate (not per item), then rounded half away from
+/// zero. Tax lines are returned in the order their rate first appears.
+pub fn compute_totals(items: &[InvoiceItem], currency: &str) -> InvoiceTotals {
+    let mut subtotal = Money::zero(currency);
+    let mut bases: Vec<(TaxRate, Money)> = Vec::new();
+
+    for item in items {
+        subtotal = subtotal + item.amount.clone();
+        match bases
+            .iter_mut()
+            .find(|(rate, _)| rate.code == item.tax_rate.code)
+        {
+            Some((_, base)) => *base = base.clone() + item.amount.clone(),
+            None => bases.push((item.tax_rate.clone(), item.amount.clone())),
+        }
+    }
+
+    let tax_lines: Vec<TaxLine> = bases
+        .into_iter()
+        .map(|(rate, taxable)| TaxLine {
+            tax: taxable.basis_points(rate.basis_points),
+            rate,
+            taxable,
+        })
+        .collect();
+    let tax_total = tax_lines.iter().fold(Money::zero(currency), |total, line| {
+        total + line.tax.clone()
+    });
+    let total = subtotal.clone() + tax_total.clone();
+
+    InvoiceTotals {
+        subtotal,
+        tax_lines,
+        tax_total,
+        total,
+    }
+}
+
+/// Parses a percentage such as `10` or `12.5` into basis points
+pub fn parse_percent(input: &str) -> Option<i64> {
+    let percent: f64 = input.trim().trim_end_matches('%').parse().ok()?;
+    if !(0.0..=100.0).contains(&percent) {
+        return None;
+    }
+    Some((percent * 100.0).round() as i64)
+}
+
+/// Formats basis points as a percentage, e.g. `1000` as `10%`
+pub fn format_percent(basis_points: i64) -> String {
+    if basis_points % 100 == 0 {
+        format!("{}%", basis_points / 100)
+    } else {
+        format!("{}%", basis_points as f64 / 100.0)
+    }
+}
@@ -0,0 +1,437 @@
+use crate::balance;
+use crate::error::Error;
+use crate::models::{BusinessProfile, CreditNote, DocumentType, InvoiceForPdf, InvoiceItem};
+use crate::money::Money;
+use crate::pdf::Source;
+use crate::tax::{self, InvoiceTotals};
+use chrono::NaiveDate;
+use minijinja::{AutoEscape, Environment, ErrorKind, Output, State, UndefinedBehavior, Value};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Plain text layouts, used by the builtin PDF backend and `--output-format
+/// txt`
+pub const INVOICE_TEXT: &str = include_str!("templates/invoice.txt");
+pub const CREDIT_NOTE_TEXT: &str = include_str!("templates/credit_note.txt");
+pub const INVOICE_MARKDOWN: &str = include_str!("templates/invoice.md");
+pub const CREDIT_NOTE_MARKDOWN: &str = include_str!("templates/credit_note.md");
+
+/// The default HTML templates, compiled in so generating works from any
+/// directory. `clinv template export` writes them out for customising.
+pub const INVOICE_HTML: &str = include_str!("../template.html");
+pub const CREDIT_NOTE_HTML: &str = include_str!("../credit_note.html");
+
+/// The name of the compiled-in template
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+/// An HTML template to fill in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Template {
+    /// The default template for this kind of document, compiled into clinv
+    Builtin(DocumentType),
+    /// Read from this file when it's used
+    File(PathBuf),
+}
+
+impl Template {
+    /// Identifies the template in error messages
+    pub fn name(&self) -> String {
+        match self {
+            Template::Builtin(DocumentType::Invoice) => "built-in template.html".to_string(),
+            Template::Builtin(DocumentType::CreditNote) => "built-in credit_note.html".to_string(),
+            Template::File(path) => path.display().to_string(),
+        }
+    }
+
+    pub fn source(&self) -> Result<Cow<'static, str>, Error> {
+        match self {
+            Template::Builtin(document) => Ok(Cow::Borrowed(builtin_html(*document))),
+            Template::File(path) => fs::read_to_string(path)
+                .map(Cow::Owned)
+                .map_err(|e| Error::Template(format!("couldn't read {}: {}", path.display(), e))),
+        }
+    }
+}
+
+/// The compiled-in HTML template for `document`
+pub fn builtin_html(document: DocumentType) -> &'static str {
+    match document {
+        DocumentType::Invoice => INVOICE_HTML,
+        DocumentType::CreditNote => CREDIT_NOTE_HTML,
+    }
+}
+
+/// Finds the template for `template`, either the name of one in
+/// `template_dir` (e.g. `minimal` for `templates/minimal.html`) or a path to
+/// an HTML file. `default` is the compiled-in template for `document`.
+pub fn resolve(
+    template: &Path,
+    template_dir: &Path,
+    document: DocumentType,
+) -> Result<Template, Error> {
+    if template == Path::new(DEFAULT_TEMPLATE) {
+        return Ok(Template::Builtin(document));
+    }
+    if !is_name(template) {
+        return Ok(Template::File(template.to_path_buf()));
+    }
+    let path = template_dir.join(template).with_extension("html");
+    if !path.is_file() {
+        return Err(Error::Template(format!(
+            "no template named {} in {}; see `clinv templates list`",
+            template.display(),
+            template_dir.display()
+        )));
+    }
+    Ok(Template::File(path))
+}
+
+/// Writes the compiled-in template for `document` to `template_dir` as
+/// `<name>.html`, refusing to replace an existing file unless `force` is set
+pub fn export(
+    document: DocumentType,
+    name: &str,
+    template_dir: &Path,
+    force: bool,
+) -> Result<PathBuf, Error> {
+    if !is_name(Path::new(name)) || name == DEFAULT_TEMPLATE {
+        return Err(Error::Validation(format!(
+            "not a valid template name: {}",
+            name
+        )));
+    }
+    let path = template_dir.join(name).with_extension("html");
+    if path.exists() && !force {
+        return Err(Error::Validation(format!(
+            "{} already exists; pass --force to replace it",
+            path.display()
+        )));
+    }
+    fs::create_dir_all(template_dir)?;
+    fs::write(&path, builtin_html(document))?;
+    Ok(path)
+}
+
+/// Bare words like `minimal` name a template; anything with a directory or
+/// extension is a path
+fn is_name(template: &Path) -> bool {
+    let mut components = template.components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) && template.extension().is_none()
+}
+
+/// The names of the templates in `template_dir`, sorted. A missing
+/// directory has none.
+pub fn list(template_dir: &Path) -> Result<Vec<String>, Error> {
+    let entries = match fs::read_dir(template_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "html") {
+            if let Some(name) = path.file_stem() {
+                names.push(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// What an invoice template is rendered with. The README lists every field.
+#[derive(Debug, Serialize)]
+pub struct InvoiceContext<'a> {
+    /// The invoice number, or `DRAFT-<id>` for drafts
+    pub number: String,
+    pub id: i32,
+    pub status: &'a str,
+    pub date: &'a str,
+    pub due_date: &'a str,
+    pub currency: &'a str,
+    pub client: ClientContext<'a>,
+    pub business: BusinessContext<'a>,
+    pub items: &'a [InvoiceItem],
+    #[serde(flatten)]
+    pub totals: InvoiceTotals,
+    pub amount_credited: Money,
+    pub amount_paid: Money,
+    pub balance_due: Money,
+}
+
+/// What a credit note template is rendered with
+#[derive(Debug, Serialize)]
+pub struct CreditNoteContext<'a> {
+    pub number: &'a str,
+    pub date: &'a str,
+    pub reason: &'a str,
+    pub currency: &'a str,
+    /// The invoice being credited
+    pub invoice: InvoiceReference,
+    pub client: ClientContext<'a>,
+    pub business: BusinessContext<'a>,
+    pub items: &'a [InvoiceItem],
+    #[serde(flatten)]
+    pub totals: InvoiceTotals,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientContext<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub phone_number: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvoiceReference {
+    pub number: String,
+    pub date: String,
+}
+
+/// The issuing business profile, plus a few fields that are awkward to
+/// work out in a template
+#[derive(Debug, Serialize)]
+pub struct BusinessContext<'a> {
+    #[serde(flatten)]
+    pub profile: &'a BusinessProfile,
+    /// The address split on line breaks
+    pub address_lines: Vec<&'a str>,
+    /// A `file://` URL for the logo, or empty if there isn't one
+    pub logo_url: String,
+}
+
+impl<'a> InvoiceContext<'a> {
+    pub fn new(invoice: &'a InvoiceForPdf) -> Self {
+        let totals = tax::compute_totals(&invoice.items, &invoice.currency);
+        let balance = balance::compute_balance(&totals, &invoice.payments, &invoice.credit_notes);
+        InvoiceContext {
+            number: invoice_number(invoice),
+            id: invoice.id,
+            status: invoice.status.as_str(),
+            date: &invoice.date,
+            due_date: &invoice.due_date,
+            currency: &invoice.currency,
+            client: ClientContext::new(invoice),
+            business: BusinessContext::new(&invoice.issuer),
+            items: &invoice.items,
+            totals,
+            amount_credited: balance.amount_credited,
+            amount_paid: balance.amount_paid,
+            balance_due: balance.balance_due,
+        }
+    }
+}
+
+impl<'a> CreditNoteContext<'a> {
+    pub fn new(credit_note: &'a CreditNote, invoice: &'a InvoiceForPdf) -> Self {
+        CreditNoteContext {
+            number: &credit_note.number,
+            date: &credit_note.date,
+            reason: &credit_note.reason,
+            currency: &credit_note.currency,
+            invoice: InvoiceReference {
+                number: invoice_number(invoice),
+                date: invoice.date.clone(),
+            },
+            client: ClientContext::new(invoice),
+            business: BusinessContext::new(&invoice.issuer),
+            items: &credit_note.items,
+            totals: tax::compute_totals(&credit_note.items, &credit_note.currency),
+        }
+    }
+}
+
+impl<'a> ClientContext<'a> {
+    fn new(invoice: &'a InvoiceForPdf) -> Self {
+        ClientContext {
+            name: &invoice.client_name,
+            email: &invoice.client_email,
+            phone_number: &invoice.client_phone_number,
+        }
+    }
+}
+
+impl<'a> BusinessContext<'a> {
+    fn new(profile: &'a BusinessProfile) -> Self {
+        let logo_url = if profile.logo_path.is_empty() {
+            String::new()
+        } else {
+            let path = fs::canonicalize(&profile.logo_path)
+                .unwrap_or_else(|_| Path::new(&profile.logo_path).to_path_buf());
+            format!("file://{}", path.display())
+        };
+        BusinessContext {
+            profile,
+            address_lines: profile.address.lines().collect(),
+            logo_url,
+        }
+    }
+}
+
+/// Drafts aren't numbered until they're issued
+fn invoice_number(invoice: &InvoiceForPdf) -> String {
+    invoice
+        .number
+        .clone()
+        .unwrap_or_else(|| format!("DRAFT-{}", invoice.id))
+}
+
+/// Renders `source` with `context` into a `format` document. `name` is used
+/// in error messages. Values are escaped in HTML and Markdown documents.
+pub fn render<S: Serialize>(
+    name: &str,
+    source: &str,
+    format: Source,
+    context: S,
+) -> Result<String, Error> {
+    let env = environment(format);
+    let template = env
+        .template_from_named_str(name, source)
+        .map_err(template_error)?;
+    template.render(context).map_err(template_error)
+}
+
+fn environment(format: Source) -> Environment<'static> {
+    let mut env = Environment::new();
+    // Misspelt fields are reported rather than left blank
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    // Client and item details can contain anything, so every value in HTML
+    // and Markdown is escaped unless the template marks it `| safe`
+    let escape = match format {
+        Source::Html => AutoEscape::Html,
+        Source::Text => AutoEscape::None,
+        Source::Markdown => AutoEscape::Custom(MARKDOWN),
+    };
+    env.set_auto_escape_callback(move |_| escape);
+    env.set_formatter(markdown_formatter);
+    // Built-in layouts end with a newline like any other text file
+    env.set_keep_trailing_newline(true);
+    // Lines holding only a block tag don't leave blank lines behind
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.add_filter("money", money);
+    env.add_filter("percent", percent);
+    env.add_filter("date", date);
+    env.add_filter("ljust", ljust);
+    env.add_filter("rjust", rjust);
+    env
+}
+
+/// The auto-escape mode of Markdown documents
+const MARKDOWN: &str = "markdown";
+
+/// Backslash-escapes the characters that would turn a value into HTML or
+/// break out of a table cell. Other modes use minijinja's own escaping.
+fn markdown_formatter(
+    out: &mut Output,
+    state: &State,
+    value: &Value,
+) -> Result<(), minijinja::Error> {
+    if state.auto_escape() != AutoEscape::Custom(MARKDOWN) || value.is_safe() {
+        return minijinja::escape_formatter(out, state, value);
+    }
+    for c in value.to_string().chars() {
+        if matches!(c, '<' | '>' | '&' | '|' | '\\') {
+            out.write_char('\\')?;
+        }
+        out.write_char(c)?;
+    }
+    Ok(())
+}
+
+fn template_error(e: minijinja::Error) -> Error {
+    let mut message = e.to_string();
+    if let Some(detail) = e.detail() {
+        if !message.contains(detail) {
+            message = format!("{} ({})", message, detail);
+        }
+    }
+    Error::Template(message)
+}
+
+/// `{{ total | money }}` gives `$1,234.50`, using the document's currency
+fn money(state: &State, amount: String) -> Result<String, minijinja::Error> {
+    let currency = state
+        .lookup("currency")
+        .and_then(|currency| currency.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let amount = Money::parse(&amount, &currency)
+        .map_err(|e| minijinja::Error::new(ErrorKind::InvalidOperation, format!("money: {}", e)))?;
+
+    let sign = if amount.minor_units < 0 { "-" } else { "" };
+    let units = amount.minor_units.unsigned_abs();
+    let whole = (units / 100).to_string();
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    Ok(format!(
+        "{}{}{}.{:02}",
+        sign,
+        currency_symbol(&currency),
+        grouped,
+        units % 100
+    ))
+}
+
+fn currency_symbol(currency: &str) -> String {
+    match currency {
+        "AUD" | "NZD" | "USD" | "CAD" | "SGD" | "HKD" => "$".to_string(),
+        "EUR" => "€".to_string(),
+        "GBP" => "£".to_string(),
+        "" => String::new(),
+        other => format!("{} ", other),
+    }
+}
+
+/// `{{ line.rate.basis_points | percent }}` gives `10%`
+fn percent(basis_points: i64) -> String {
+    tax::format_percent(basis_points)
+}
+
+/// `{{ due_date | date("%d %B %Y") }}` reformats a `YYYY-MM-DD` date. The
+/// format defaults to `%-d %B %Y`, e.g. `6 June 2025`. The format comes from
+/// the template, so the result isn't escaped.
+fn date(value: String, format: Option<String>) -> Result<Value, minijinja::Error> {
+    let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| {
+        minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            format!("date: expected YYYY-MM-DD, got {}", value),
+        )
+    })?;
+    let format = format.as_deref().unwrap_or("%-d %B %Y");
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format)).map_err(|_| {
+        minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            format!("date: invalid format {}", format),
+        )
+    })?;
+    Ok(Value::from_safe_string(formatted))
+}
+
+/// `{{ item.description | ljust(40) }}` pads with spaces to 40 characters,
+/// for lining up columns in text templates
+fn ljust(value: Value, width: usize) -> String {
+    let value = value.to_string();
+    let padding = width.saturating_sub(value.chars().count());
+    format!("{}{}", value, " ".repeat(padding))
+}
+
+/// Like `ljust`, but pads on the left
+fn rjust(value: Value, width: usize) -> String {
+    let value = value.to_string();
+    let padding = width.saturating_sub(value.chars().count());
+    format!("{}{}", " ".repeat(padding), value)
+}
@@ -1,23 +1,31 @@
-use crate::models::InvoiceForPdf;
-use chrono::{Duration, NaiveDate};
+use crate::database;
+use crate::error::Error;
+use crate::models::{CreditNote, InvoiceForPdf, NewItem};
+use crate::money::Money;
+use crate::pdf::{Renderer, Source};
+use crate::tax::DEFAULT_TAX_CODE;
+use crate::template::{self, CreditNoteContext, InvoiceContext, Template};
+use chrono::NaiveDate;
 use email_address::EmailAddress;
 use phonenumber::{country, parse};
-use rusqlite::{params, Connection};
-use std::path::Path;
-use std::error::Error;
+use rusqlite::{Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
 use std::{
     fs,
     io::{self, Write},
 };
-use wkhtmltopdf::{self, Orientation, PdfApplication};
 
-// Generic prompt function
+// Generic prompt function. Running out of input (e.g. stdin closed in a
+// script) ends clinv with a usage error rather than prompting forever.
 pub fn prompt(prompt_text: &str) -> String {
     print!("{}", prompt_text);
-    io::stdout().flush().unwrap();
-
     let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+    let read = io::stdout().flush().and_then(|()| io::stdin().read_line(&mut input));
+    if !matches!(read, Ok(n) if n > 0) {
+        let error = Error::Usage("no input to answer the prompt; pass the value as a flag".to_string());
+        eprintln!("\nError: {}", error);
+        std::process::exit(i32::from(error.exit_code()));
+    }
 
     input.trim().to_string()
 }
@@ -36,6 +44,26 @@ pub fn prompt_for_f64(prompt_msg: &str) -> f64 {
     }
 }
 
+pub fn prompt_for_money(prompt_msg: &str, currency: &str) -> Money {
+    loop {
+        let input = prompt(prompt_msg);
+        match Money::parse(&input, currency) {
+            Ok(money) => return money,
+            Err(e) => println!("Not a valid amount: {}", e),
+        }
+    }
+}
+
+/// Prompts for a value, returning `default` if the input is left empty
+pub fn prompt_with_default(prompt_msg: &str, default: &str) -> String {
+    let input = prompt(&format!("{} [{}]: ", prompt_msg, default));
+    if input.is_empty() {
+        default.to_string()
+    } else {
+        input
+    }
+}
+
 pub fn prompt_for_str(prompt_msg: &str) -> String {
     loop {
         let input = prompt(prompt_msg);
@@ -47,111 +75,258 @@ pub fn prompt_for_str(prompt_msg: &str) -> String {
     }
 }
 
-pub fn read_and_add_invoice_items(connection: &Connection, invoice_id: i64) -> Vec<i64> {
+pub fn read_and_add_invoice_items(
+    connection: &Connection,
+    invoice_id: i64,
+) -> Result<Vec<i64>, Error> {
     let mut item_ids = Vec::new();
-    loop {
-        let description = prompt("Description (leave empty to finish): ");
-        if description.is_empty() {
-            break;
-        }
+    let currency = database::get_invoice_currency(connection, invoice_id)
+        .optional()?
+        .ok_or_else(|| Error::InvoiceNotFound(invoice_id.to_string()))?;
+    while let Some(item) = prompt_for_item(connection, &currency) {
+        // Insert into the database
+        let item_id = database::add_invoice_item(
+            connection,
+            invoice_id,
+            &item.description,
+            item.hours,
+            &item.rate,
+            &item.tax_code,
+        )?;
+
+        item_ids.push(item_id);
 
-        let hours = prompt_for_f64("Hours: ");
+        println!("Item added.\n");
+    }
+    Ok(item_ids)
+}
 
-        let rate = prompt_for_f64("Rate: ");
+/// Prompts for the fields of one item, returning `None` once the user leaves
+/// the description empty
+pub fn prompt_for_item(connection: &Connection, currency: &str) -> Option<NewItem> {
+    let description = prompt("Description (leave empty to finish): ");
+    if description.is_empty() {
+        return None;
+    }
 
-        let amount = hours * rate;
+    let hours = prompt_for_f64("Hours: ");
 
-        // Insert into the database
-        connection.execute(
-            "INSERT INTO invoice_item (invoice_id, description, hours, rate, amount) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![invoice_id, description, hours, rate, amount],
-        ).expect("Failed to insert item");
+    let rate = prompt_for_money("Rate: ", currency);
 
-        // Get the last inserted id
-        let item_id = connection.last_insert_rowid();
+    let tax_code = loop {
+        let code = prompt_with_default("Tax code", DEFAULT_TAX_CODE);
+        match database::get_tax_rate(connection, &code) {
+            Ok(Some(_)) => break code,
+            _ => println!("Unknown tax code: {}", code),
+        }
+    };
 
-        item_ids.push(item_id);
+    Some(NewItem {
+        description,
+        hours,
+        rate,
+        tax_code,
+    })
+}
 
-        println!("Item added.\n");
-    }
-    item_ids
+/// Checks a phone number is valid for `region`, a two letter country code
+/// like `AU`. Numbers starting with `+` are accepted from any country.
+pub fn is_valid_phone(number: &str, region: &str) -> bool {
+    let region = region.parse::<country::Id>().ok();
+    region.is_some() && parse(region, number).is_ok()
 }
 
-pub fn is_valid_phone(number: &str) -> bool {
-    parse(Some(country::AU), number).is_ok()
+pub fn is_valid_phone_region(region: &str) -> bool {
+    region.parse::<country::Id>().is_ok()
 }
 
 pub fn is_valid_email(email: &str) -> bool {
     EmailAddress::is_valid(email)
 }
 
+/// Checks an Australian Business Number against its checksum. Spaces are
+/// ignored, so `51 824 753 556` is accepted.
+pub fn is_valid_abn(abn: &str) -> bool {
+    const WEIGHTS: [u32; 11] = [10, 1, 3, 5, 7, 9, 11, 13, 15, 17, 19];
+    let digits: Vec<u32> = abn
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+    if digits.len() != 11 || digits[0] == 0 {
+        return false;
+    }
+    // The leading digit has one subtracted before weighting
+    let sum: u32 = digits
+        .iter()
+        .zip(WEIGHTS)
+        .enumerate()
+        .map(|(i, (digit, weight))| if i == 0 { (digit - 1) * weight } else { digit * weight })
+        .sum();
+    sum.is_multiple_of(89)
+}
+
+/// Parses payment terms such as `net14`, `Net 30`, `14` or `receipt` (due on
+/// receipt) into a number of days
+pub fn parse_payment_terms(terms: &str) -> Option<i64> {
+    let normalised = terms.trim().to_lowercase().replace(' ', "");
+    match normalised.as_str() {
+        "receipt" | "dueonreceipt" | "cod" => Some(0),
+        _ => {
+            let days = normalised.strip_prefix("net").unwrap_or(&normalised);
+            days.parse::<i64>().ok().filter(|days| *days >= 0)
+        }
+    }
+}
+
+pub fn format_payment_terms(days: i64) -> String {
+    if days == 0 {
+        "Due on receipt".to_string()
+    } else {
+        format!("Net {}", days)
+    }
+}
+
+/// Renders an invoice, saved in a folder for its month under `output_dir`.
+/// `template` is only read if the renderer lays out HTML.
 pub fn generate_pdf(
     invoice: &InvoiceForPdf,
-    template: &str,
-) -> Result<String, Box<dyn Error>> {
-    let html = fs::read_to_string(template)?;
+    template: &Template,
+    output_dir: &Path,
+    renderer: &dyn Renderer,
+) -> Result<PathBuf, Error> {
+    let document = render_invoice(invoice, renderer.source(), template)?;
+    let pdf_path = invoice_path(invoice, output_dir, "pdf")?;
+    renderer.render("Invoice", &document, &pdf_path)?;
+    Ok(pdf_path)
+}
+
+/// Saves an invoice as HTML (filled in from `template`), plain text or
+/// Markdown, where its PDF would go
+pub fn generate_invoice_document(
+    invoice: &InvoiceForPdf,
+    source: Source,
+    template: &Template,
+    output_dir: &Path,
+) -> Result<PathBuf, Error> {
+    let document = render_invoice(invoice, source, template)?;
+    let path = invoice_path(invoice, output_dir, source.extension())?;
+    fs::write(&path, document)?;
+    Ok(path)
+}
 
-    let date = NaiveDate::parse_from_str(&invoice.date, "%Y-%m-%d")?;
-    let due_date = date + Duration::days(30); // Net 30
-    let due_date_str = due_date.format("%Y-%m-%d").to_string();
+/// Renders a credit note against `invoice`, saved alongside the invoices for
+/// the month it was issued
+pub fn generate_credit_note_pdf(
+    credit_note: &CreditNote,
+    invoice: &InvoiceForPdf,
+    template: &Template,
+    output_dir: &Path,
+    renderer: &dyn Renderer,
+) -> Result<PathBuf, Error> {
+    let document = render_credit_note(credit_note, invoice, renderer.source(), template)?;
+    let pdf_path = credit_note_path(credit_note, invoice, output_dir, "pdf")?;
+    renderer.render("Credit Note", &document, &pdf_path)?;
+    Ok(pdf_path)
+}
 
-    let invoice_number = format!("INV-{}-{}", invoice.date, invoice.id);
+/// Saves a credit note as HTML, plain text or Markdown, where its PDF would
+/// go
+pub fn generate_credit_note_document(
+    credit_note: &CreditNote,
+    invoice: &InvoiceForPdf,
+    source: Source,
+    template: &Template,
+    output_dir: &Path,
+) -> Result<PathBuf, Error> {
+    let document = render_credit_note(credit_note, invoice, source, template)?;
+    let path = credit_note_path(credit_note, invoice, output_dir, source.extension())?;
+    fs::write(&path, document)?;
+    Ok(path)
+}
 
+/// Fills in the invoice's HTML template, or the built-in text or Markdown
+/// layout
+fn render_invoice(
+    invoice: &InvoiceForPdf,
+    source: Source,
+    template: &Template,
+) -> Result<String, Error> {
+    let context = InvoiceContext::new(invoice);
+    match source {
+        Source::Html => template::render(&template.name(), &template.source()?, source, context),
+        Source::Text => template::render("invoice.txt", template::INVOICE_TEXT, source, context),
+        Source::Markdown => template::render("invoice.md", template::INVOICE_MARKDOWN, source, context),
+    }
+}
+
+/// Like `render_invoice`, for a credit note against `invoice`
+fn render_credit_note(
+    credit_note: &CreditNote,
+    invoice: &InvoiceForPdf,
+    source: Source,
+    template: &Template,
+) -> Result<String, Error> {
+    let context = CreditNoteContext::new(credit_note, invoice);
+    match source {
+        Source::Html => template::render(&template.name(), &template.source()?, source, context),
+        Source::Text => template::render("credit_note.txt", template::CREDIT_NOTE_TEXT, source, context),
+        Source::Markdown => {
+            template::render("credit_note.md", template::CREDIT_NOTE_MARKDOWN, source, context)
+        }
+    }
+}
+
+/// Where an invoice is saved, e.g. `invoices/2026-05/04-3-Alice.pdf`. The
+/// month's folder is created if needed.
+fn invoice_path(
+    invoice: &InvoiceForPdf,
+    output_dir: &Path,
+    extension: &str,
+) -> Result<PathBuf, Error> {
+    document_path(
+        &invoice.date,
+        &invoice.id.to_string(),
+        &invoice.client_name,
+        output_dir,
+        extension,
+    )
+}
+
+fn credit_note_path(
+    credit_note: &CreditNote,
+    invoice: &InvoiceForPdf,
+    output_dir: &Path,
+    extension: &str,
+) -> Result<PathBuf, Error> {
+    document_path(
+        &credit_note.date,
+        &credit_note.number,
+        &invoice.client_name,
+        output_dir,
+        extension,
+    )
+}
+
+fn document_path(
+    date: &str,
+    name: &str,
+    client_name: &str,
+    output_dir: &Path,
+    extension: &str,
+) -> Result<PathBuf, Error> {
+    let date = parse_document_date(date)?;
     let year_month = date.format("%Y-%m").to_string();
     let day = date.format("%d").to_string();
-    let sanitized_client_name = invoice.client_name.replace("/", "-").replace(" ", "-");
-    let filename = format!("{}-{}-{}.pdf", day, invoice.id, sanitized_client_name);
-    let folder_path = Path::new("invoices").join(&year_month);
+    let sanitized_client_name = client_name.replace("/", "-").replace(" ", "-");
+    let filename = format!("{}-{}-{}.{}", day, name, sanitized_client_name, extension);
+    let folder_path = output_dir.join(&year_month);
     fs::create_dir_all(&folder_path)?;
-    let pdf_path = folder_path.join(&filename);
-    let pdf_path_str = pdf_path.to_str().unwrap();
-
-    // Replace template placeholders with invoice values
-    let mut filled_template = html
-        .replace("{invoice_id}", &invoice_number)
-        .replace("{client_name}", &invoice.client_name)
-        .replace("{client_email}", &invoice.client_email)
-        .replace("{client_phone_number}", &invoice.client_phone_number)
-        .replace("{date}", &invoice.date)
-        .replace("{due_date}", &due_date_str);
-
-    // Generate item list as text
-    let num_items = invoice.items.len() - 1;
-    let mut cur_item = 0;
-    let mut total_cost = 0.00;
-    let items_text: String = invoice
-        .items
-        .iter()
-        .map(|item| {
-            if cur_item == num_items {
-                cur_item += 1;
-                total_cost += item.amount;
-                format!(
-                    "<tr class=\"item last\"><td>{}</td><td style=\"text-align: left\">{}</td><td style=\"text-align: right;\">${:.2}</td><td style=\"text-align: right;\">${:.2}</td></tr>",
-                    item.description, item.hours, item.rate, item.amount
-                )
-            } else {
-                cur_item += 1;
-                total_cost += item.amount;
-                format!(
-                    "<tr class=\"item last\"><td>{}</td><td style=\"text-align: left\">{}</td><td style=\"text-align: right;\">${:.2}</td><td style=\"text-align: right;\">${:.2}</td></tr>",
-                    item.description, item.hours, item.rate, item.amount
-                )
-            }
-        })
-        .collect();
-
-    filled_template = filled_template.replace("{items}", &items_text);
-    filled_template = filled_template.replace("{total}", &format!("{:.2}", total_cost));
-    let pdf_app = PdfApplication::new().expect("Failed to init PDF application");
-    let mut pdfout = pdf_app
-        .builder()
-        .orientation(Orientation::Portrait)
-        .title("Invoice")
-        .build_from_html(filled_template)
-        .expect("failed to build pdf");
-
-    pdfout.save(pdf_path_str).expect("failed to save file");
-
-    Ok(pdf_path_str.to_string())
+    Ok(folder_path.join(&filename))
+}
+
+fn parse_document_date(date: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| Error::Validation(format!("not a valid date, expected YYYY-MM-DD: {}", date)))
 }
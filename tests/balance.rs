@@ -0,0 +1,83 @@
+use clinv::balance::compute_balance;
+use clinv::models::{CreditNote, InvoiceItem, Payment, TaxRate};
+use clinv::money::Money;
+use clinv::tax::InvoiceTotals;
+
+fn totals(total_minor: i64) -> InvoiceTotals {
+    InvoiceTotals {
+        subtotal: Money::new(total_minor, "AUD"),
+        tax_lines: vec![],
+        tax_total: Money::zero("AUD"),
+        total: Money::new(total_minor, "AUD"),
+    }
+}
+
+fn payment(amount_minor: i64) -> Payment {
+    Payment {
+        id: 0,
+        invoice_id: 1,
+        date: "2025-07-01".to_string(),
+        amount: Money::new(amount_minor, "AUD"),
+        method: "bank transfer".to_string(),
+        reference: None,
+    }
+}
+
+fn credit_note(amount_minor: i64, basis_points: i64) -> CreditNote {
+    CreditNote {
+        id: 0,
+        invoice_id: 1,
+        number: "CN-2025-0001".to_string(),
+        date: "2025-07-01".to_string(),
+        reason: "Overbilled".to_string(),
+        currency: "AUD".to_string(),
+        items: vec![InvoiceItem {
+            id: 0,
+            description: "Design".to_string(),
+            hours: 1.0,
+            rate: Money::new(amount_minor, "AUD"),
+            amount: Money::new(amount_minor, "AUD"),
+            tax_rate: TaxRate {
+                code: "gst".to_string(),
+                name: "GST".to_string(),
+                basis_points,
+            },
+        }],
+    }
+}
+
+#[test]
+fn test_balance_with_no_payments() {
+    let balance = compute_balance(&totals(11000), &[], &[]);
+    assert_eq!(balance.amount_paid, Money::zero("AUD"));
+    assert_eq!(balance.balance_due, Money::new(11000, "AUD"));
+    assert!(!balance.is_settled());
+}
+
+#[test]
+fn test_balance_with_partial_payments() {
+    let balance = compute_balance(&totals(11000), &[payment(5000), payment(2500)], &[]);
+    assert_eq!(balance.amount_paid, Money::new(7500, "AUD"));
+    assert_eq!(balance.balance_due, Money::new(3500, "AUD"));
+    assert!(!balance.is_settled());
+}
+
+#[test]
+fn test_balance_settled_and_overpaid() {
+    let balance = compute_balance(&totals(11000), &[payment(11000)], &[]);
+    assert!(balance.is_settled());
+
+    let balance = compute_balance(&totals(11000), &[payment(12000)], &[]);
+    assert_eq!(balance.balance_due, Money::new(-1000, "AUD"));
+    assert!(balance.is_settled());
+}
+
+#[test]
+fn test_balance_with_credit_notes() {
+    // Credits are taken off including their tax
+    let balance = compute_balance(&totals(11000), &[payment(5500)], &[credit_note(5000, 1000)]);
+    assert_eq!(balance.amount_credited, Money::new(5500, "AUD"));
+    assert_eq!(balance.amount_paid, Money::new(5500, "AUD"));
+    assert_eq!(balance.balance_due, Money::zero("AUD"));
+    assert!(balance.is_settled());
+}
@@ -1,4 +1,8 @@
-use clinv::cli::{map_command_words, Cli, Commands};
+use clinv::cli::{
+    map_command_words, Cli, Commands, ConfigCommands, CreditNoteCommands, DocumentFormat, InvoiceCommands, NumberingCommands, OutputFormat, ProfileCommands, ReportCommands, TemplateCommands,
+};
+use clinv::models::{DocumentType, InvoiceStatus};
+use clinv::pdf::Source;
 use clap::Parser;
 
 #[test]
@@ -6,11 +10,20 @@ fn test_map_command_words_new_client() {
     let words = vec!["new".to_string(), "client".to_string()];
     let cmd = map_command_words(&words);
     match cmd {
-        Some(Commands::NewClient { name, nickname, email, phone_number }) => {
+        Some(Commands::NewClient {
+            name,
+            nickname,
+            email,
+            phone_number,
+            payment_terms,
+            template,
+        }) => {
             assert!(name.is_none());
             assert!(nickname.is_none());
             assert!(email.is_none());
             assert!(phone_number.is_none());
+            assert!(payment_terms.is_none());
+            assert!(template.is_none());
         }
         _ => panic!("Expected Some(Commands::NewClient)"),
     }
@@ -21,7 +34,19 @@ fn test_map_command_words_new_invoice() {
     let words = vec!["new".to_string(), "invoice".to_string()];
     let cmd = map_command_words(&words);
     match cmd {
-        Some(Commands::NewInvoice { client_nickname }) => assert!(client_nickname.is_none()),
+        Some(Commands::NewInvoice {
+            client_nickname,
+            due_date,
+            profile,
+            items,
+            items_file,
+        }) => {
+            assert!(client_nickname.is_none());
+            assert!(due_date.is_none());
+            assert!(profile.is_none());
+            assert!(items.is_empty());
+            assert!(items_file.is_none());
+        }
         _ => panic!("Expected Some(Commands::NewInvoice)"),
     }
 }
@@ -30,7 +55,12 @@ fn test_map_command_words_new_invoice() {
 fn test_map_command_words_list_clients() {
     let words = vec!["list".to_string(), "clients".to_string()];
     let cmd = map_command_words(&words);
-    assert!(matches!(cmd, Some(Commands::ListClients)));
+    assert!(matches!(
+        cmd,
+        Some(Commands::ListClients {
+            format: OutputFormat::Table
+        })
+    ));
 }
 
 #[test]
@@ -38,7 +68,15 @@ fn test_map_command_words_list_invoices() {
     let words = vec!["list".to_string(), "invoices".to_string()];
     let cmd = map_command_words(&words);
     match cmd {
-        Some(Commands::ListInvoices { client_nickname }) => assert!(client_nickname.is_none()),
+        Some(Commands::ListInvoices {
+            client_nickname,
+            status,
+            format,
+        }) => {
+            assert!(client_nickname.is_none());
+            assert!(status.is_none());
+            assert_eq!(format, OutputFormat::Table);
+        }
         _ => panic!("Expected Some(Commands::ListInvoices)"),
     }
 }
@@ -68,7 +106,11 @@ fn test_map_command_words_generate() {
     let words = vec!["generate".to_string()];
     let cmd = map_command_words(&words);
     match cmd {
-        Some(Commands::Generate { invoice_id }) => assert!(invoice_id.is_none()),
+        Some(Commands::Generate { invoice_id, output_format, template }) => {
+            assert!(invoice_id.is_none());
+            assert_eq!(output_format, DocumentFormat::Pdf);
+            assert!(template.is_none());
+        }
         _ => panic!("Expected Some(Commands::Generate)"),
     }
 }
@@ -92,7 +134,13 @@ fn test_cli_parse_new_client() {
         "--phone-number", "1234",
     ]);
     match cli.command {
-        Some(Commands::NewClient { name, nickname, email, phone_number }) => {
+        Some(Commands::NewClient {
+            name,
+            nickname,
+            email,
+            phone_number,
+            ..
+        }) => {
             assert_eq!(name.as_deref(), Some("Alice"));
             assert_eq!(nickname.as_deref(), Some("al"));
             assert_eq!(email.as_deref(), Some("alice@example.com"));
@@ -106,7 +154,7 @@ fn test_cli_parse_new_client() {
 fn test_cli_parse_list_clients() {
     let cli = Cli::parse_from(["clinv", "list-clients"]);
     match cli.command {
-        Some(Commands::ListClients) => {}
+        Some(Commands::ListClients { format }) => assert_eq!(format, OutputFormat::Table),
         _ => panic!("Expected ListClients"),
     }
 }
@@ -118,3 +166,382 @@ fn test_raw_command_input_collection() {
     // If no subcommand matched, raw_command.words will contain ["foo", "bar", "baz"]
     assert_eq!(cli.raw_command.words, vec!["foo", "bar", "baz"]);
 }
+
+#[test]
+fn test_map_command_words_status_transitions() {
+    let words = vec!["issue".to_string(), "invoice".to_string(), "3".to_string()];
+    match map_command_words(&words) {
+        Some(Commands::IssueInvoice { invoice_id }) => assert_eq!(invoice_id.as_deref(), Some("3")),
+        _ => panic!("Expected Some(Commands::IssueInvoice)"),
+    }
+
+    let words = vec!["mark".to_string(), "paid".to_string()];
+    match map_command_words(&words) {
+        Some(Commands::MarkPaid { invoice_id }) => assert!(invoice_id.is_none()),
+        _ => panic!("Expected Some(Commands::MarkPaid)"),
+    }
+}
+
+#[test]
+fn test_cli_parse_list_invoices_by_status() {
+    let cli = Cli::parse_from(["clinv", "list-invoices", "--status", "sent"]);
+    match cli.command {
+        Some(Commands::ListInvoices { status, .. }) => {
+            assert_eq!(status, Some(InvoiceStatus::Sent));
+        }
+        _ => panic!("Expected ListInvoices"),
+    }
+
+    assert!(Cli::try_parse_from(["clinv", "list-invoices", "--status", "lost"]).is_err());
+}
+
+#[test]
+fn test_cli_parse_report_aging() {
+    let cli = Cli::parse_from(["clinv", "report", "aging", "--format", "csv"]);
+    match cli.command {
+        Some(Commands::Report {
+            report: ReportCommands::Aging { format, as_of },
+        }) => {
+            assert_eq!(format, OutputFormat::Csv);
+            assert!(as_of.is_none());
+        }
+        _ => panic!("Expected Report Aging"),
+    }
+
+    let cli = Cli::parse_from(["clinv", "report", "aging"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Report {
+            report: ReportCommands::Aging {
+                format: OutputFormat::Table,
+                ..
+            }
+        })
+    ));
+}
+
+#[test]
+fn test_cli_parse_profile_edit() {
+    let cli = Cli::parse_from([
+        "clinv", "profile", "edit", "caleb",
+        "--business-name", "Walker Consulting",
+        "--abn", "51 824 753 556",
+    ]);
+    match cli.command {
+        Some(Commands::Profile {
+            action: Some(ProfileCommands::Edit { name, business_name, abn, email, .. }),
+        }) => {
+            assert_eq!(name, Some("caleb".to_string()));
+            assert_eq!(business_name, Some("Walker Consulting".to_string()));
+            assert_eq!(abn, Some("51 824 753 556".to_string()));
+            assert!(email.is_none());
+        }
+        _ => panic!("Expected Profile Edit"),
+    }
+
+    // On its own, `profile` shows the default profile
+    let cli = Cli::parse_from(["clinv", "profile"]);
+    assert!(matches!(cli.command, Some(Commands::Profile { action: None })));
+}
+
+#[test]
+fn test_cli_parse_new_invoice_with_profile() {
+    let cli = Cli::parse_from(["clinv", "new-invoice", "--client-nickname", "al", "--profile", "sam"]);
+    match cli.command {
+        Some(Commands::NewInvoice { client_nickname, profile, .. }) => {
+            assert_eq!(client_nickname, Some("al".to_string()));
+            assert_eq!(profile, Some("sam".to_string()));
+        }
+        _ => panic!("Expected NewInvoice"),
+    }
+}
+
+#[test]
+fn test_cli_parse_new_invoice_with_items() {
+    let cli = Cli::parse_from([
+        "clinv", "new-invoice", "-c", "al",
+        "--item", "Design;2;120",
+        "--item", "Hosting;1;25.00;gst-free",
+        "--items-file", "items.csv",
+    ]);
+    match cli.command {
+        Some(Commands::NewInvoice { items, items_file, .. }) => {
+            assert_eq!(items, vec!["Design;2;120", "Hosting;1;25.00;gst-free"]);
+            assert_eq!(items_file, Some("items.csv".into()));
+        }
+        _ => panic!("Expected NewInvoice"),
+    }
+}
+
+#[test]
+fn test_cli_parse_numbering_set() {
+    let cli = Cli::parse_from([
+        "clinv", "numbering", "set",
+        "--prefix", "CW-",
+        "--yearly-reset", "false",
+        "--next-number", "42",
+    ]);
+    match cli.command {
+        Some(Commands::Numbering {
+            action: NumberingCommands::Set { profile, document, prefix, padding, yearly_reset, next_number },
+        }) => {
+            assert!(profile.is_none());
+            assert_eq!(document, DocumentType::Invoice);
+            assert_eq!(prefix, Some("CW-".to_string()));
+            assert!(padding.is_none());
+            assert_eq!(yearly_reset, Some(false));
+            assert_eq!(next_number, Some(42));
+        }
+        _ => panic!("Expected Numbering Set"),
+    }
+}
+
+#[test]
+fn test_cli_parse_credit_note() {
+    let cli = Cli::parse_from(["clinv", "credit-note", "new", "--invoice-id", "3", "--full", "--reason", "Overbilled"]);
+    match cli.command {
+        Some(Commands::CreditNote {
+            action: CreditNoteCommands::New { invoice_id, full, reason },
+        }) => {
+            assert_eq!(invoice_id, Some("3".to_string()));
+            assert!(full);
+            assert_eq!(reason, Some("Overbilled".to_string()));
+        }
+        _ => panic!("Expected CreditNote New"),
+    }
+
+    let cli = Cli::parse_from(["clinv", "numbering", "show", "--document", "credit-note"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Numbering {
+            action: NumberingCommands::Show { document: DocumentType::CreditNote, .. }
+        })
+    ));
+}
+
+#[test]
+fn test_map_command_words_edit_client() {
+    let words = vec!["edit".to_string(), "client".to_string(), "al".to_string()];
+    match map_command_words(&words) {
+        Some(Commands::EditClient { client_nickname, name, email, .. }) => {
+            assert_eq!(client_nickname, Some("al".to_string()));
+            assert!(name.is_none());
+            assert!(email.is_none());
+        }
+        _ => panic!("Expected Some(Commands::EditClient)"),
+    }
+}
+
+#[test]
+fn test_cli_parse_invoice_items() {
+    let cli = Cli::parse_from([
+        "clinv", "invoice", "add-item",
+        "--invoice-id", "4",
+        "--description", "Hosting",
+        "--hours", "1",
+        "--rate", "25.00",
+    ]);
+    match cli.command {
+        Some(Commands::Invoice {
+            action: InvoiceCommands::AddItem { invoice_id, description, hours, rate, tax_code },
+        }) => {
+            assert_eq!(invoice_id, Some("4".to_string()));
+            assert_eq!(description, Some("Hosting".to_string()));
+            assert_eq!(hours, Some(1.0));
+            assert_eq!(rate, Some("25.00".to_string()));
+            assert!(tax_code.is_none());
+        }
+        _ => panic!("Expected Invoice AddItem"),
+    }
+
+    let words = vec!["remove".to_string(), "item".to_string(), "4".to_string(), "7".to_string()];
+    match map_command_words(&words) {
+        Some(Commands::Invoice {
+            action: InvoiceCommands::RemoveItem { invoice_id, item_id },
+        }) => {
+            assert_eq!(invoice_id, Some("4".to_string()));
+            assert_eq!(item_id, Some("7".to_string()));
+        }
+        _ => panic!("Expected Invoice RemoveItem"),
+    }
+}
+
+#[test]
+fn test_cli_parse_list_formats() {
+    let cli = Cli::parse_from(["clinv", "list-invoices", "--format", "json", "--status", "sent"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::ListInvoices { format: OutputFormat::Json, status: Some(InvoiceStatus::Sent), .. })
+    ));
+
+    let cli = Cli::parse_from(["clinv", "list-clients", "-f", "csv"]);
+    assert!(matches!(cli.command, Some(Commands::ListClients { format: OutputFormat::Csv })));
+
+    let cli = Cli::parse_from(["clinv", "profile", "show", "sam", "--format", "json"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Profile { action: Some(ProfileCommands::Show { name: Some(_), format: OutputFormat::Json }) })
+    ));
+
+    assert!(Cli::try_parse_from(["clinv", "list-tax-rates", "--format", "xml"]).is_err());
+}
+
+#[test]
+fn test_cli_parse_db_path() {
+    let cli = Cli::parse_from(["clinv", "list-clients", "--db", "/tmp/walker.db"]);
+    assert_eq!(cli.db, Some("/tmp/walker.db".into()));
+
+    // Also accepted before the command, and for natural language commands
+    let cli = Cli::parse_from(["clinv", "--db", "other.db", "list", "clients"]);
+    assert_eq!(cli.db, Some("other.db".into()));
+    assert_eq!(cli.raw_command.words, vec!["list", "clients"]);
+}
+
+#[test]
+fn test_cli_parse_config() {
+    let cli = Cli::parse_from(["clinv", "--config", "work.toml", "config", "set", "currency", "NZD"]);
+    assert_eq!(cli.config, Some("work.toml".into()));
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Config { action: ConfigCommands::Set { ref key, ref value } })
+            if key == "currency" && value == "NZD"
+    ));
+
+    let cli = Cli::parse_from(["clinv", "config", "get", "output_dir"]);
+    assert!(matches!(cli.command, Some(Commands::Config { action: ConfigCommands::Get { .. } })));
+
+    let cli = Cli::parse_from(["clinv", "config", "list"]);
+    assert!(matches!(cli.command, Some(Commands::Config { action: ConfigCommands::List { format: OutputFormat::Table } })));
+    let cli = Cli::parse_from(["clinv", "config", "list", "--format", "json"]);
+    assert!(matches!(cli.command, Some(Commands::Config { action: ConfigCommands::List { format: OutputFormat::Json } })));
+}
+
+#[test]
+fn test_parse_generate_output_format() {
+    let cli = Cli::parse_from(["clinv", "generate", "-i", "3", "--output-format", "md"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Generate { output_format: DocumentFormat::Md, .. })
+    ));
+    assert_eq!(DocumentFormat::Txt.source(), Some(Source::Text));
+    assert_eq!(DocumentFormat::Pdf.source(), None);
+
+    assert!(Cli::try_parse_from(["clinv", "generate", "--output-format", "docx"]).is_err());
+}
+
+#[test]
+fn test_parse_templates() {
+    let cli = Cli::parse_from(["clinv", "templates", "list"]);
+    assert!(matches!(cli.command, Some(Commands::Templates { action: TemplateCommands::List { format: OutputFormat::Table } })));
+    let cli = Cli::parse_from(["clinv", "templates", "list", "--format", "csv"]);
+    assert!(matches!(cli.command, Some(Commands::Templates { action: TemplateCommands::List { format: OutputFormat::Csv } })));
+
+    let cli = Cli::parse_from(["clinv", "template", "export", "--credit-note"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Templates {
+            action: TemplateCommands::Export { ref name, credit_note: true, force: false }
+        }) if name == "custom"
+    ));
+
+    let cli = Cli::parse_from(["clinv", "generate", "-i", "3", "--template", "minimal"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Generate { template: Some(ref template), .. }) if template == "minimal"
+    ));
+
+    let cli = Cli::parse_from(["clinv", "edit-client", "-c", "al", "--template", "default"]);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::EditClient { template: Some(ref template), .. }) if template == "default"
+    ));
+}
+
+#[test]
+fn test_binary_runs_bare_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_clinv"))
+        .arg("--db")
+        .arg(dir.path().join("clinv.db"))
+        .arg("--config")
+        .arg(dir.path().join("config.toml"))
+        .arg("profile")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No business profile set up yet"));
+}
+
+#[test]
+fn test_binary_rejects_negative_hours() {
+    let dir = tempfile::tempdir().unwrap();
+    let clinv = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_clinv"))
+            .arg("--db")
+            .arg(dir.path().join("clinv.db"))
+            .arg("--config")
+            .arg(dir.path().join("config.toml"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let connection = rusqlite::Connection::open(dir.path().join("clinv.db")).unwrap();
+    clinv::migrations::migrate(&connection).unwrap();
+    clinv::database::new_client(&connection, "Ivy", "ivy", "ivy@example.com", "12345", None).unwrap();
+    let invoice_id = clinv::database::new_invoice(&connection, "ivy", "2026-05-01", None, None, 30, "AUD").unwrap();
+
+    let invoice_id = invoice_id.to_string();
+    let add = ["invoice", "add-item", "--invoice-id", &invoice_id, "--description", "Design", "--hours", "-1", "--rate", "100"];
+    let output = clinv(&add);
+    assert_eq!(output.status.code(), Some(i32::from(clinv::error::exit_code::VALIDATION)), "{}", String::from_utf8_lossy(&output.stderr));
+    let count: i64 = connection.query_row("SELECT COUNT(*) FROM invoice_item", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_binary_config_does_not_open_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("clinv.db");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_clinv"))
+        .arg("--db")
+        .arg(&db_path)
+        .arg("--config")
+        .arg(dir.path().join("config.toml"))
+        .args(["config", "set", "currency", "NZD"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!db_path.exists());
+    assert!(std::fs::read_to_string(dir.path().join("config.toml")).unwrap().contains("NZD"));
+}
+
+#[test]
+fn test_binary_does_not_prompt_without_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let clinv = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_clinv"))
+            .arg("--db")
+            .arg(dir.path().join("clinv.db"))
+            .arg("--config")
+            .arg(dir.path().join("config.toml"))
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .unwrap()
+    };
+    let connection = rusqlite::Connection::open(dir.path().join("clinv.db")).unwrap();
+    clinv::migrations::migrate(&connection).unwrap();
+    clinv::database::new_client(&connection, "Ivy", "ivy", "ivy@example.com", "12345", None).unwrap();
+    let invoice_id = clinv::database::new_invoice(&connection, "ivy", "2026-05-01", None, None, 30, "AUD").unwrap();
+    connection.execute("UPDATE invoice SET status = 'issued' WHERE id = ?1", [invoice_id]).unwrap();
+    let invoice_id = invoice_id.to_string();
+
+    // A bad flag is reported instead of asking again
+    let output = clinv(&["record-payment", "--invoice-id", &invoice_id, "--amount", "lots"]);
+    assert_eq!(output.status.code(), Some(i32::from(clinv::error::exit_code::VALIDATION)));
+
+    // Closed stdin ends the prompt
+    let output = clinv(&["record-payment", "--invoice-id", &invoice_id]);
+    assert_eq!(output.status.code(), Some(i32::from(clinv::error::exit_code::USAGE)));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pass the value as a flag"));
+}
@@ -1,6 +1,8 @@
-use clinv::cli::Commands;
-use clinv::commands;
+use clinv::cli::{Commands, ConfigCommands, DocumentFormat, InvoiceCommands, OutputFormat, TemplateCommands};
+use clinv::commands::{self, Outcome};
+use clinv::config::Config;
 use clinv::database::init_db;
+use clinv::error::Error;
 
 
 fn setup() -> rusqlite::Connection {
@@ -12,8 +14,14 @@ fn setup() -> rusqlite::Connection {
 #[test]
 fn test_execute_command_list_clients() {
     let connection = setup();
-    let result = commands::execute_command(&connection, Commands::ListClients);
-    assert!(result.is_ok());
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::ListClients {
+            format: OutputFormat::Table,
+        },
+    );
+    assert_eq!(result.unwrap(), Outcome::Printed);
 }
 
 #[test]
@@ -29,6 +37,7 @@ fn test_execute_command_delete_client() {
     // Delete client
     let result = commands::execute_command(
         &connection,
+        &Config::default(),
         Commands::DeleteClient {
             client_nickname: Some("bobby".to_string()),
         },
@@ -47,8 +56,11 @@ fn test_execute_command_list_invoices_empty() {
 
     let result = commands::execute_command(
         &connection,
+        &Config::default(),
         Commands::ListInvoices {
             client_nickname: None,
+            status: None,
+            format: OutputFormat::Json,
         },
     );
     assert!(result.is_ok());
@@ -68,7 +80,7 @@ fn test_execute_command_delete_invoice() {
 
     connection
         .execute(
-            "INSERT INTO invoice (client_id, date) VALUES (?1, '2025-06-06')",
+            "INSERT INTO invoice (client_id, date, due_date) VALUES (?1, '2025-06-06', '2025-07-06')",
             [client_id],
         )
         .unwrap();
@@ -76,13 +88,14 @@ fn test_execute_command_delete_invoice() {
 
     // Insert an invoice item for completeness
     connection.execute(
-        "INSERT INTO invoice_item (invoice_id, description, hours, rate, amount) VALUES (?1, 'service', 2, 50, 100)",
+        "INSERT INTO invoice_item (invoice_id, description, hours, rate_minor, amount_minor) VALUES (?1, 'service', 2, 5000, 10000)",
         [invoice_id],
     ).unwrap();
 
     // Delete the invoice
     let result = commands::execute_command(
         &connection,
+        &Config::default(),
         Commands::DeleteInvoice {
             invoice_id: Some(invoice_id.to_string()),
         },
@@ -102,3 +115,804 @@ fn test_execute_command_delete_invoice() {
     let count: i64 = stmt.query_row([invoice_id], |row| row.get(0)).unwrap();
     assert_eq!(count, 0);
 }
+
+#[test]
+fn test_execute_command_invoice_lifecycle() {
+    let connection = setup();
+
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number) VALUES ('Dan', 'dan', 'dan@example.com', '777')",
+        [],
+    ).unwrap();
+    let client_id = connection.last_insert_rowid();
+    connection
+        .execute(
+            "INSERT INTO invoice (client_id, date, due_date) VALUES (?1, '2025-06-06', '2025-07-06')",
+            [client_id],
+        )
+        .unwrap();
+    let invoice_id = Some(connection.last_insert_rowid().to_string());
+
+    // Can't skip straight to paid
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::MarkPaid {
+            invoice_id: invoice_id.clone(),
+        },
+    );
+    assert!(result.is_err());
+
+    for command in [
+        Commands::IssueInvoice {
+            invoice_id: invoice_id.clone(),
+        },
+        Commands::MarkSent {
+            invoice_id: invoice_id.clone(),
+        },
+        Commands::MarkPaid {
+            invoice_id: invoice_id.clone(),
+        },
+    ] {
+        assert!(commands::execute_command(&connection, &Config::default(), command).is_ok());
+    }
+
+    let status: String = connection
+        .query_row("SELECT status FROM invoice", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(status, "paid");
+
+    // Paid invoices can't be voided
+    let result = commands::execute_command(&connection, &Config::default(), Commands::VoidInvoice { invoice_id });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_execute_command_record_payment() {
+    let connection = setup();
+
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number) VALUES ('Eve', 'eve', 'eve@example.com', '888')",
+        [],
+    ).unwrap();
+    let client_id = connection.last_insert_rowid();
+    connection
+        .execute(
+            "INSERT INTO invoice (client_id, date, due_date, status) VALUES (?1, '2025-06-06', '2025-07-06', 'sent')",
+            [client_id],
+        )
+        .unwrap();
+    let invoice_id = connection.last_insert_rowid();
+    connection.execute(
+        "INSERT INTO invoice_item (invoice_id, description, hours, rate_minor, amount_minor, tax_code) VALUES (?1, 'service', 2, 5000, 10000, 'gst')",
+        [invoice_id],
+    ).unwrap();
+
+    let pay = |amount: &str| {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::RecordPayment {
+                invoice_id: Some(invoice_id.to_string()),
+                amount: Some(amount.to_string()),
+                date: Some("2025-07-01".to_string()),
+                method: None,
+                reference: None,
+            },
+        )
+    };
+
+    // Bad amounts from flags are errors rather than prompts
+    assert!(matches!(pay("lots"), Err(Error::Validation(_))));
+    assert!(matches!(pay("0"), Err(Error::Validation(_))));
+
+    // Partial payment leaves the invoice sent
+    assert!(pay("60.00").is_ok());
+    let status: String = connection
+        .query_row("SELECT status FROM invoice", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(status, "sent");
+
+    // Paying the remaining $50 (including GST) settles it
+    assert!(pay("50.00").is_ok());
+    let status: String = connection
+        .query_row("SELECT status FROM invoice", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(status, "paid");
+
+    // No further payments against a paid invoice
+    assert!(pay("1.00").is_err());
+}
+
+#[test]
+fn test_execute_command_credit_note() {
+    use clinv::cli::CreditNoteCommands;
+
+    let connection = setup();
+
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number) VALUES ('Fred', 'fred', 'fred@example.com', '999')",
+        [],
+    ).unwrap();
+    let client_id = connection.last_insert_rowid();
+    connection
+        .execute(
+            "INSERT INTO invoice (client_id, date, due_date, currency) VALUES (?1, '2025-06-06', '2025-07-06', 'EUR')",
+            [client_id],
+        )
+        .unwrap();
+    let invoice_id = connection.last_insert_rowid();
+    connection.execute(
+        "INSERT INTO invoice_item (invoice_id, description, hours, rate_minor, amount_minor, tax_code) VALUES (?1, 'service', 2, 5000, 10000, 'gst')",
+        [invoice_id],
+    ).unwrap();
+
+    let credit = || {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::CreditNote {
+                action: CreditNoteCommands::New {
+                    invoice_id: Some(invoice_id.to_string()),
+                    full: true,
+                    reason: Some("Cancelled".to_string()),
+                },
+            },
+        )
+    };
+
+    // Drafts can simply be edited or deleted instead
+    assert!(credit().is_err());
+
+    commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::IssueInvoice {
+            invoice_id: Some(invoice_id.to_string()),
+        },
+    )
+    .unwrap();
+    assert!(credit().is_ok());
+    // Nothing left to credit, reported in the invoice's currency
+    let Err(Error::Validation(message)) = credit() else {
+        panic!("Expected a validation error");
+    };
+    assert_eq!(message, format!("Credit of 110.00 EUR is more than the 0.00 EUR left to credit on invoice {}", invoice_id));
+
+    let count: i64 = connection
+        .query_row("SELECT COUNT(*) FROM credit_note", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_execute_command_edit_client() {
+    let connection = setup();
+
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number, payment_terms_days) VALUES ('Gia', 'gia', 'gia@example.com', '0412345678', 14)",
+        [],
+    ).unwrap();
+
+    let edit = |email: &str, payment_terms: &str| {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::EditClient {
+                client_nickname: Some("gia".to_string()),
+                name: None,
+                nickname: None,
+                email: Some(email.to_string()),
+                phone_number: None,
+                payment_terms: Some(payment_terms.to_string()),
+                template: None,
+            },
+        )
+    };
+
+    assert!(edit("gia@newmail.com", "default").is_ok());
+    let (name, email, terms): (String, String, Option<i64>) = connection
+        .query_row("SELECT name, email, payment_terms_days FROM client", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .unwrap();
+    assert_eq!(name, "Gia");
+    assert_eq!(email, "gia@newmail.com");
+    assert_eq!(terms, None);
+
+    assert!(edit("not an email", "net7").is_err());
+    assert!(edit("gia@newmail.com", "whenever").is_err());
+}
+
+#[test]
+fn test_execute_command_invoice_items() {
+    let connection = setup();
+
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number) VALUES ('Hal', 'hal', 'hal@example.com', '555')",
+        [],
+    ).unwrap();
+    let invoice_id = clinv::database::new_invoice(&connection, "hal", "2026-04-01", None, None, 30, "AUD").unwrap();
+
+    let add = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::Invoice {
+            action: InvoiceCommands::AddItem {
+                invoice_id: Some(invoice_id.to_string()),
+                description: Some("Consulting".to_string()),
+                hours: Some(2.0),
+                rate: Some("150".to_string()),
+                tax_code: None,
+            },
+        },
+    );
+    assert!(add.is_ok());
+    let item_id = connection.last_insert_rowid();
+
+    let edit = |rate: &str| {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::Invoice {
+                action: InvoiceCommands::EditItem {
+                    invoice_id: Some(invoice_id.to_string()),
+                    item_id: Some(item_id.to_string()),
+                    description: None,
+                    hours: None,
+                    rate: Some(rate.to_string()),
+                    tax_code: None,
+                },
+            },
+        )
+    };
+    assert!(edit("120").is_ok());
+    assert!(edit("lots").is_err());
+    for hours in [f64::NAN, f64::INFINITY, 0.0, -1.0] {
+        let result = commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::Invoice {
+                action: InvoiceCommands::EditItem {
+                    invoice_id: Some(invoice_id.to_string()),
+                    item_id: Some(item_id.to_string()),
+                    description: None,
+                    hours: Some(hours),
+                    rate: None,
+                    tax_code: None,
+                },
+            },
+        );
+        assert!(matches!(result, Err(Error::Validation(_))), "{}: {:?}", hours, result);
+    }
+    let (description, rate, amount, tax_code): (String, i64, i64, String) = connection
+        .query_row(
+            "SELECT description, rate_minor, amount_minor, tax_code FROM invoice_item WHERE id = ?1",
+            [item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(description, "Consulting");
+    assert_eq!(rate, 12000);
+    assert_eq!(amount, 24000);
+    assert_eq!(tax_code, "gst");
+
+    let remove = |id: &str| {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::Invoice {
+                action: InvoiceCommands::RemoveItem {
+                    invoice_id: Some(invoice_id.to_string()),
+                    item_id: Some(id.to_string()),
+                },
+            },
+        )
+    };
+    assert!(remove("999").is_err());
+    assert!(remove(&item_id.to_string()).is_ok());
+    let count: i64 = connection
+        .query_row("SELECT COUNT(*) FROM invoice_item", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_execute_command_new_invoice_with_items() {
+    let connection = setup();
+
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number) VALUES ('Ida', 'ida', 'ida@example.com', '555')",
+        [],
+    ).unwrap();
+
+    let new_invoice = |items: &[&str]| {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::NewInvoice {
+                client_nickname: Some("ida".to_string()),
+                due_date: None,
+                profile: None,
+                items: items.iter().map(|s| s.to_string()).collect(),
+                items_file: None,
+            },
+        )
+    };
+
+    // A bad item stops the invoice being created at all
+    assert!(new_invoice(&["Design;2;120", "Hosting;1;25;vat"]).is_err());
+    let count: i64 = connection
+        .query_row("SELECT COUNT(*) FROM invoice", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+
+    assert!(new_invoice(&["Design;2;120", "Hosting;1;25;gst-free"]).is_ok());
+    let items: Vec<(String, i64, String)> = connection
+        .prepare("SELECT description, amount_minor, tax_code FROM invoice_item ORDER BY id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        items,
+        vec![
+            ("Design".to_string(), 24000, "gst".to_string()),
+            ("Hosting".to_string(), 2500, "gst-free".to_string()),
+        ]
+    );
+
+    // An empty items file is an error rather than a reason to prompt
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("items.json");
+    std::fs::write(&path, "[]").unwrap();
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::NewInvoice {
+            client_nickname: Some("ida".to_string()),
+            due_date: None,
+            profile: None,
+            items: Vec::new(),
+            items_file: Some(path),
+        },
+    );
+    assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+    let count: i64 = connection
+        .query_row("SELECT COUNT(*) FROM invoice", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_execute_command_typed_errors() {
+    let connection = setup();
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::NewInvoice {
+            client_nickname: Some("nobody".to_string()),
+            due_date: None,
+            profile: None,
+            items: vec!["Design;1;100".to_string()],
+            items_file: None,
+        },
+    );
+    assert!(matches!(result, Err(Error::ClientNotFound(nickname)) if nickname == "nobody"));
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::IssueInvoice {
+            invoice_id: Some("42".to_string()),
+        },
+    );
+    assert!(matches!(result, Err(Error::InvoiceNotFound(id)) if id == "42"));
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::NewInvoice {
+            client_nickname: Some("nobody".to_string()),
+            due_date: Some("next week".to_string()),
+            profile: None,
+            items: Vec::new(),
+            items_file: None,
+        },
+    );
+    assert!(matches!(result, Err(Error::Validation(_))));
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::CreditNote {
+            action: clinv::cli::CreditNoteCommands::Generate {
+                credit_note_id: Some("7".to_string()),
+                output_format: DocumentFormat::Pdf,
+            },
+        },
+    );
+    assert!(matches!(result, Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_execute_command_outcomes() {
+    let connection = setup();
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::NewClient {
+            name: Some("Jo".to_string()),
+            nickname: Some("jo".to_string()),
+            email: Some("jo@example.com".to_string()),
+            phone_number: Some("0412345678".to_string()),
+            payment_terms: None,
+            template: None,
+        },
+    );
+    assert!(matches!(result, Ok(Outcome::Created(_))));
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::NewInvoice {
+            client_nickname: Some("jo".to_string()),
+            due_date: None,
+            profile: None,
+            items: vec!["Design;1;100".to_string()],
+            items_file: None,
+        },
+    );
+    let Ok(Outcome::Created(invoice_id)) = result else {
+        panic!("Expected a created invoice, got {:?}", result);
+    };
+
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::IssueInvoice {
+            invoice_id: Some(invoice_id.to_string()),
+        },
+    );
+    assert_eq!(result.unwrap(), Outcome::Saved);
+
+    // Failures to generate are reported rather than printed and ignored
+    let result = commands::execute_command(
+        &connection,
+        &Config::default(),
+        Commands::Generate {
+            invoice_id: Some("999".to_string()),
+            output_format: DocumentFormat::Pdf,
+            template: None,
+        },
+    );
+    assert_eq!(result.unwrap_err().exit_code(), clinv::error::exit_code::NOT_FOUND);
+
+    // Text output doesn't need a PDF backend
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        output_dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::Generate {
+            invoice_id: Some(invoice_id.to_string()),
+            output_format: DocumentFormat::Txt,
+            template: None,
+        },
+    );
+    let Ok(Outcome::Generated(path)) = result else {
+        panic!("Expected a generated document, got {:?}", result);
+    };
+    assert_eq!(path.extension().unwrap(), "txt");
+    assert!(path.starts_with(dir.path()));
+    assert!(std::fs::read_to_string(&path).unwrap().contains("Design"));
+}
+
+#[test]
+fn test_config_commands() {
+    let connection = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config::load(&dir.path().join("config.toml")).unwrap();
+
+    let set = |key: &str, value: &str| {
+        commands::execute_command(
+            &connection,
+            &config,
+            Commands::Config {
+                action: ConfigCommands::Set {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                },
+            },
+        )
+    };
+    assert_eq!(set("currency", "NZD").unwrap(), Outcome::Saved);
+    assert!(matches!(set("currency", "dollars"), Err(Error::Validation(_))));
+    assert!(matches!(set("colour", "blue"), Err(Error::Validation(_))));
+
+    let saved = Config::load(&dir.path().join("config.toml")).unwrap();
+    assert_eq!(saved.currency, "NZD");
+
+    // New invoices pick up the configured currency and terms
+    let config = Config {
+        currency: "NZD".to_string(),
+        payment_terms_days: 7,
+        ..Config::default()
+    };
+    connection.execute(
+        "INSERT INTO client (name, nickname, email, phone_number) VALUES ('Kai', 'kai', 'kai@example.com', '555')",
+        [],
+    ).unwrap();
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::NewInvoice {
+            client_nickname: Some("kai".to_string()),
+            due_date: None,
+            profile: None,
+            items: vec!["Design;1;100".to_string()],
+            items_file: None,
+        },
+    );
+    let Ok(Outcome::Created(invoice_id)) = result else {
+        panic!("Expected a created invoice, got {:?}", result);
+    };
+    let invoice = clinv::database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.currency, "NZD");
+    assert_eq!(invoice.items[0].rate.currency, "NZD");
+}
+
+#[test]
+fn test_generate_picks_template() {
+    let connection = setup();
+    let dir = tempfile::tempdir().unwrap();
+    let template_dir = dir.path().join("templates");
+    std::fs::create_dir(&template_dir).unwrap();
+    std::fs::write(template_dir.join("plain.html"), "Plain {{ client.name }}").unwrap();
+    std::fs::write(template_dir.join("fancy.html"), "Fancy {{ client.name }}").unwrap();
+    let config = Config {
+        template: "fancy".into(),
+        template_dir,
+        output_dir: dir.path().join("invoices"),
+        ..Config::default()
+    };
+
+    let new_client = |nickname: &str, template: Option<&str>| {
+        commands::execute_command(
+            &connection,
+            &config,
+            Commands::NewClient {
+                name: Some("Rae".to_string()),
+                nickname: Some(nickname.to_string()),
+                email: Some("rae@example.com".to_string()),
+                phone_number: Some("0412345678".to_string()),
+                payment_terms: None,
+                template: template.map(str::to_string),
+            },
+        )
+    };
+    assert!(new_client("rae", Some("plain")).is_ok());
+    // Unknown names are caught before the client is created
+    assert!(matches!(new_client("ray", Some("missing")), Err(Error::Template(_))));
+    assert!(clinv::database::get_client(&connection, "ray").unwrap().is_none());
+
+    let invoice_id = clinv::database::new_invoice(&connection, "rae", "2026-02-03", None, None, 30, "AUD").unwrap();
+    let generate = |template: Option<&str>| {
+        let result = commands::execute_command(
+            &connection,
+            &config,
+            Commands::Generate {
+                invoice_id: Some(invoice_id.to_string()),
+                output_format: DocumentFormat::Html,
+                template: template.map(str::to_string),
+            },
+        );
+        let Ok(Outcome::Generated(path)) = result else {
+            panic!("Expected a generated document, got {:?}", result);
+        };
+        std::fs::read_to_string(path).unwrap()
+    };
+
+    // The client's template, unless --template is given
+    assert_eq!(generate(None), "Plain Rae");
+    assert_eq!(generate(Some("fancy")), "Fancy Rae");
+
+    // Text is laid out by clinv, so the client's HTML template is passed over
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::Generate {
+            invoice_id: Some(invoice_id.to_string()),
+            output_format: DocumentFormat::Txt,
+            template: None,
+        },
+    );
+    let Ok(Outcome::Generated(path)) = result else {
+        panic!("Expected a generated document, got {:?}", result);
+    };
+    assert!(!std::fs::read_to_string(path).unwrap().contains("Plain"));
+
+    // Asking for a template that won't be used is a mistake
+    for output_format in [DocumentFormat::Txt, DocumentFormat::Md] {
+        let result = commands::execute_command(
+            &connection,
+            &config,
+            Commands::Generate {
+                invoice_id: Some(invoice_id.to_string()),
+                output_format,
+                template: Some("fancy".to_string()),
+            },
+        );
+        assert!(matches!(result, Err(Error::Usage(_))), "{:?}", result);
+    }
+    // The builtin PDF backend lays out the text version too
+    #[cfg(feature = "printpdf")]
+    {
+        let mut config = config.clone();
+        config.pdf_backend = clinv::pdf::PdfBackend::Builtin;
+        let result = commands::execute_command(
+            &connection,
+            &config,
+            Commands::Generate {
+                invoice_id: Some(invoice_id.to_string()),
+                output_format: DocumentFormat::Pdf,
+                template: Some("fancy".to_string()),
+            },
+        );
+        assert!(matches!(result, Err(Error::Usage(_))), "{:?}", result);
+    }
+    // So by default custom templates need wkhtmltopdf
+    #[cfg(not(feature = "wkhtmltopdf"))]
+    {
+        let result = commands::execute_command(
+            &connection,
+            &config,
+            Commands::Generate {
+                invoice_id: Some(invoice_id.to_string()),
+                output_format: DocumentFormat::Pdf,
+                template: Some("fancy".to_string()),
+            },
+        );
+        assert!(matches!(result, Err(Error::Pdf(_))), "{:?}", result);
+    }
+    // Unless it's the built-in one, which every format has
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::Generate {
+            invoice_id: Some(invoice_id.to_string()),
+            output_format: DocumentFormat::Txt,
+            template: Some("default".to_string()),
+        },
+    );
+    assert!(matches!(result, Ok(Outcome::Generated(_))), "{:?}", result);
+
+    // Putting the client back on the configured default
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::EditClient {
+            client_nickname: Some("rae".to_string()),
+            name: None,
+            nickname: None,
+            email: None,
+            phone_number: None,
+            payment_terms: None,
+            template: Some("default".to_string()),
+        },
+    );
+    assert_eq!(result.unwrap(), Outcome::Saved);
+    assert_eq!(generate(None), "Fancy Rae");
+
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::Templates {
+            action: TemplateCommands::List { format: OutputFormat::Table },
+        },
+    );
+    assert_eq!(result.unwrap(), Outcome::Printed);
+
+    // The built-in template can be exported and then picked by name
+    let result = commands::execute_command(
+        &connection,
+        &config,
+        Commands::Templates {
+            action: TemplateCommands::Export {
+                name: "mine".to_string(),
+                credit_note: false,
+                force: false,
+            },
+        },
+    );
+    assert_eq!(result.unwrap(), Outcome::Generated(config.template_dir.join("mine.html")));
+    assert!(generate(Some("mine")).contains("Rae<br />"));
+    assert!(generate(Some("default")).contains("Rae<br />"));
+}
+
+#[test]
+fn test_numbering_cannot_rewind() {
+    use clinv::cli::NumberingCommands;
+    use clinv::models::DocumentType;
+
+    let connection = setup();
+    clinv::database::new_client(&connection, "Uma", "uma", "uma@example.com", "0412345678", None).unwrap();
+    for _ in 0..2 {
+        let result = commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::NewInvoice {
+                client_nickname: Some("uma".to_string()),
+                due_date: None,
+                profile: None,
+                items: vec!["Design;1;100".to_string()],
+                items_file: None,
+            },
+        );
+        let Ok(Outcome::Created(invoice_id)) = result else {
+            panic!("Expected a created invoice, got {:?}", result);
+        };
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::IssueInvoice {
+                invoice_id: Some(invoice_id.to_string()),
+            },
+        )
+        .unwrap();
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::CreditNote {
+                action: clinv::cli::CreditNoteCommands::New {
+                    invoice_id: Some(invoice_id.to_string()),
+                    full: true,
+                    reason: Some("Cancelled".to_string()),
+                },
+            },
+        )
+        .unwrap();
+    }
+
+    let set_document = |document: DocumentType, prefix: Option<&str>, next_number: Option<i64>| {
+        commands::execute_command(
+            &connection,
+            &Config::default(),
+            Commands::Numbering {
+                action: NumberingCommands::Set {
+                    profile: None,
+                    document,
+                    prefix: prefix.map(str::to_string),
+                    padding: None,
+                    yearly_reset: None,
+                    next_number,
+                },
+            },
+        )
+    };
+
+    let set = |prefix: Option<&str>, next_number: Option<i64>| set_document(DocumentType::Invoice, prefix, next_number);
+
+    // Numbers 1 and 2 are already on issued invoices
+    for next_number in [1, 2] {
+        let result = set(None, Some(next_number));
+        assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+    }
+    assert_eq!(set(None, Some(3)).unwrap(), Outcome::Saved);
+
+    // A fresh prefix can start again, but switching back can't
+    assert_eq!(set(Some("CW-"), Some(1)).unwrap(), Outcome::Saved);
+    let result = set(Some("INV-"), None);
+    assert_eq!(result.unwrap_err().exit_code(), clinv::error::exit_code::VALIDATION);
+
+    // Credit notes have their own sequence, with the same protection
+    let result = set_document(DocumentType::CreditNote, None, Some(1));
+    assert!(matches!(result, Err(Error::Validation(_))), "{:?}", result);
+    assert_eq!(set_document(DocumentType::CreditNote, None, Some(3)).unwrap(), Outcome::Saved);
+}
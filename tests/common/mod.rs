@@ -0,0 +1,54 @@
+//! Fixtures shared by the integration tests. Not every test file uses all of them.
+#![allow(dead_code)]
+
+use clinv::models::{BusinessProfile, InvoiceForPdf, InvoiceItem, InvoiceStatus, TaxRate};
+use clinv::money::Money;
+
+pub fn gst() -> TaxRate {
+    TaxRate { code: "gst".to_string(), name: "GST".to_string(), basis_points: 1000 }
+}
+
+pub fn gst_free() -> TaxRate {
+    TaxRate { code: "gst-free".to_string(), name: "GST free".to_string(), basis_points: 0 }
+}
+
+pub fn item(description: &str, hours: f64, rate_minor: i64, tax_rate: TaxRate) -> InvoiceItem {
+    let rate = Money::new(rate_minor, "AUD");
+    InvoiceItem {
+        id: 1,
+        description: description.to_string(),
+        hours,
+        amount: rate.times(hours),
+        rate,
+        tax_rate,
+    }
+}
+
+/// An issued invoice 7 to Alice, dated 2 March 2026
+pub fn invoice(items: Vec<InvoiceItem>) -> InvoiceForPdf {
+    InvoiceForPdf {
+        id: 7,
+        number: Some("INV-2026-0007".to_string()),
+        client_name: "Alice".to_string(),
+        client_email: "alice@example.com".to_string(),
+        client_phone_number: "0412345678".to_string(),
+        client_template: None,
+        date: "2026-03-02".to_string(),
+        due_date: "2026-04-01".to_string(),
+        currency: "AUD".to_string(),
+        status: InvoiceStatus::Issued,
+        items,
+        payments: Vec::new(),
+        issuer: BusinessProfile {
+            business_name: "Walker Consulting".to_string(),
+            address: "1 Main St\nSydney NSW 2000".to_string(),
+            ..BusinessProfile::default()
+        },
+        credit_notes: Vec::new(),
+    }
+}
+
+/// The same invoice before it's issued, so without a number
+pub fn draft_invoice(items: Vec<InvoiceItem>) -> InvoiceForPdf {
+    InvoiceForPdf { number: None, status: InvoiceStatus::Draft, ..invoice(items) }
+}
@@ -0,0 +1,116 @@
+use clinv::config::{Config, ConfigError, KEYS};
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn test_load_missing_file_uses_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.template, PathBuf::from("default"));
+    assert_eq!(config.credit_note_template, PathBuf::from("default"));
+    assert_eq!(config.output_dir, PathBuf::from("invoices"));
+    assert_eq!(config.template_dir, PathBuf::from("templates"));
+    assert_eq!(config.payment_terms_days, 30);
+    assert_eq!(config.phone_region, "AU");
+    assert_eq!(config.currency, "AUD");
+    assert_eq!(config.path, Some(path.clone()));
+
+    // Loading doesn't create the file
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_load_partial_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    fs::write(&path, "output_dir = \"/srv/invoices\"\ncurrency = \"NZD\"\n").unwrap();
+
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.output_dir, PathBuf::from("/srv/invoices"));
+    assert_eq!(config.currency, "NZD");
+    // Anything not in the file keeps its default
+    assert_eq!(config.payment_terms_days, 30);
+    assert_eq!(config.phone_region, "AU");
+}
+
+#[test]
+fn test_load_rejects_bad_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+
+    fs::write(&path, "currency = ").unwrap();
+    assert!(matches!(Config::load(&path), Err(ConfigError::Parse { .. })));
+
+    // Typos aren't silently ignored
+    fs::write(&path, "curency = \"NZD\"\n").unwrap();
+    assert!(matches!(Config::load(&path), Err(ConfigError::Parse { .. })));
+
+    // Values are checked as `set` would check them
+    for contents in ["currency = \"JPY\"\n", "phone_region = \"Australia\"\n", "payment_terms_days = -7\n"] {
+        fs::write(&path, contents).unwrap();
+        assert!(matches!(Config::load(&path), Err(ConfigError::InvalidValue { .. })), "{}", contents);
+    }
+    fs::write(&path, "currency = \"nzd\"\n").unwrap();
+    assert_eq!(Config::load(&path).unwrap().currency, "NZD");
+}
+
+#[test]
+fn test_set_and_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("clinv").join("config.toml");
+
+    let mut config = Config::load(&path).unwrap();
+    config.set("payment_terms_days", "net14").unwrap();
+    config.set("phone_region", "nz").unwrap();
+    config.set("currency", "nzd").unwrap();
+    config.set("template", "templates/invoice.html").unwrap();
+    config.save().unwrap();
+
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.get("payment_terms_days").unwrap(), "14");
+    assert_eq!(config.get("phone_region").unwrap(), "NZ");
+    assert_eq!(config.get("currency").unwrap(), "NZD");
+    assert_eq!(config.template, PathBuf::from("templates/invoice.html"));
+}
+
+#[test]
+fn test_set_rejects_bad_values() {
+    let mut config = Config::default();
+
+    assert!(matches!(
+        config.set("payment_terms_days", "soon"),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        config.set("phone_region", "Australia"),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        config.set("currency", "dollars"),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        config.set("currency", "jpy"),
+        Err(ConfigError::InvalidValue { .. })
+    ));
+    assert!(matches!(config.set("colour", "blue"), Err(ConfigError::UnknownKey(_))));
+    assert!(matches!(config.get("colour"), Err(ConfigError::UnknownKey(_))));
+
+    // Nothing changed
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn test_every_key_can_be_read() {
+    let config = Config::default();
+    for key in KEYS {
+        assert!(config.get(key).is_ok(), "{}", key);
+    }
+}
+
+#[test]
+fn test_save_without_path() {
+    assert!(matches!(Config::default().save(), Err(ConfigError::NoPath)));
+}
@@ -1,4 +1,6 @@
-use clinv::database::{self, init_db};
+use clinv::database::{self, init_db, ClientError, InvoiceError, ProfileError};
+use clinv::models::{BusinessProfile, DocumentType, InvoiceStatus, NewItem};
+use clinv::money::Money;
 use rusqlite::Connection;
 
 fn setup() -> Connection {
@@ -11,20 +13,21 @@ fn setup() -> Connection {
 fn test_new_and_get_client() {
     let connection = setup();
 
-    database::new_client(&connection, "Alice", "al", "alice@example.com", "12345").unwrap();
+    database::new_client(&connection, "Alice", "al", "alice@example.com", "12345", None).unwrap();
     let clients = database::get_clients(&connection).unwrap();
     assert_eq!(clients.len(), 1);
     assert_eq!(clients[0].name, "Alice");
     assert_eq!(clients[0].nickname, "al");
     assert_eq!(clients[0].email, "alice@example.com");
     assert_eq!(clients[0].phone_number, "12345");
+    assert_eq!(clients[0].payment_terms_days, None);
 }
 
 #[test]
 fn test_delete_client() {
     let connection = setup();
 
-    database::new_client(&connection, "Bob", "bobby", "bob@example.com", "67890").unwrap();
+    database::new_client(&connection, "Bob", "bobby", "bob@example.com", "67890", None).unwrap();
     let clients_size = database::get_clients(&connection).unwrap().len();
     let client_nickname = "bobby".to_string();
     database::delete_client(&connection, &client_nickname).unwrap();
@@ -37,18 +40,19 @@ fn test_new_and_get_invoice() {
     let connection = setup();
 
     // Add a client first
-    database::new_client(&connection, "Carol", "car", "carol@example.com", "55555").unwrap();
+    database::new_client(&connection, "Carol", "car", "carol@example.com", "55555", None).unwrap();
     let client_id = database::get_clients(&connection).unwrap()[0].id.to_string();
     let client_nickname = database::get_clients(&connection).unwrap()[0].nickname.to_string();
     let date = "2025-06-06";
-    let invoice_id = database::new_invoice(&connection, &client_nickname, date).unwrap();
+    let invoice_id = database::new_invoice(&connection, &client_nickname, date, None, None, 30, "AUD").unwrap();
     assert_eq!(invoice_id, 1);
 
     // There should be a new invoice
-    let invoices = database::get_invoices(&connection, Some(&client_nickname)).unwrap();
+    let invoices = database::get_invoices(&connection, Some(&client_nickname), None).unwrap();
     assert_eq!(invoices.len(), 1);
     assert_eq!(invoices[0].client_id.to_string(), client_id);
     assert_eq!(invoices[0].date, date);
+    assert_eq!(invoices[0].due_date, "2025-07-06");
     // Invoice should start with zero items
     assert_eq!(invoices[0].items.len(), 0);
 }
@@ -57,11 +61,503 @@ fn test_new_and_get_invoice() {
 fn test_delete_invoice() {
     let connection = setup();
 
-    database::new_client(&connection, "Dave", "davey", "dave@example.com", "11111").unwrap();
+    database::new_client(&connection, "Dave", "davey", "dave@example.com", "11111", None).unwrap();
     let client_nickname = database::get_clients(&connection).unwrap()[0].nickname.to_string();
     let date = "2025-06-06";
-    let invoice_id = database::new_invoice(&connection, &client_nickname, date).unwrap();
+    let invoice_id = database::new_invoice(&connection, &client_nickname, date, None, None, 30, "AUD").unwrap();
     database::delete_invoice(&connection, &invoice_id.to_string()).unwrap();
-    let invoices = database::get_invoices(&connection, Some(&client_nickname)).unwrap();
+    let invoices = database::get_invoices(&connection, Some(&client_nickname), None).unwrap();
     assert_eq!(invoices.len(), 0);
 }
+
+#[test]
+fn test_default_tax_rates_seeded() {
+    let connection = setup();
+
+    let rates = database::get_tax_rates(&connection).unwrap();
+    let gst = rates.iter().find(|rate| rate.code == "gst").unwrap();
+    assert_eq!(gst.basis_points, 1000);
+    assert!(rates.iter().any(|rate| rate.code == "gst-free"));
+    assert!(rates.iter().any(|rate| rate.code == "input-taxed"));
+}
+
+#[test]
+fn test_new_tax_rate() {
+    let connection = setup();
+
+    database::new_tax_rate(&connection, "wet", "Wine equalisation tax", 2900).unwrap();
+    let rate = database::get_tax_rate(&connection, "wet").unwrap().unwrap();
+    assert_eq!(rate.name, "Wine equalisation tax");
+    assert_eq!(rate.basis_points, 2900);
+    assert!(database::get_tax_rate(&connection, "missing").unwrap().is_none());
+}
+
+#[test]
+fn test_add_invoice_item_with_tax() {
+    let connection = setup();
+
+    database::new_client(&connection, "Erin", "erin", "erin@example.com", "22222", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "erin", "2025-06-06", None, None, 30, "AUD").unwrap();
+    let rate = Money::new(12000, "AUD");
+    database::add_invoice_item(&connection, invoice_id, "Design", 2.5, &rate, "gst").unwrap();
+
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.items.len(), 1);
+    assert_eq!(invoice.items[0].amount, Money::new(30000, "AUD"));
+    assert_eq!(invoice.items[0].tax_rate.code, "gst");
+    assert_eq!(invoice.items[0].tax_rate.basis_points, 1000);
+}
+
+#[test]
+fn test_set_invoice_status() {
+    let connection = setup();
+
+    database::new_client(&connection, "Fay", "fay", "fay@example.com", "33333", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "fay", "2025-06-06", None, None, 30, "AUD").unwrap().to_string();
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    assert_eq!(invoices[0].status, InvoiceStatus::Draft);
+
+    database::set_invoice_status(&connection, &invoice_id, InvoiceStatus::Issued, "2025-06-07 09:00:00")
+        .unwrap();
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    assert_eq!(invoices[0].status, InvoiceStatus::Issued);
+    assert_eq!(invoices[0].issued_at.as_deref(), Some("2025-06-07 09:00:00"));
+    assert!(invoices[0].sent_at.is_none());
+
+    let result = database::set_invoice_status(&connection, &invoice_id, InvoiceStatus::Paid, "2025-06-08 09:00:00");
+    assert!(matches!(
+        result,
+        Err(InvoiceError::InvalidTransition {
+            from: InvoiceStatus::Issued,
+            to: InvoiceStatus::Paid
+        })
+    ));
+
+    let result = database::set_invoice_status(&connection, "999", InvoiceStatus::Void, "2025-06-08 09:00:00");
+    assert!(matches!(result, Err(InvoiceError::InvoiceNotFound(_))));
+}
+
+#[test]
+fn test_get_invoices_filtered_by_status() {
+    let connection = setup();
+
+    database::new_client(&connection, "Gus", "gus", "gus@example.com", "44444", None).unwrap();
+    database::new_invoice(&connection, "gus", "2025-06-06", None, None, 30, "AUD").unwrap();
+    let voided = database::new_invoice(&connection, "gus", "2025-06-07", None, None, 30, "AUD").unwrap();
+    database::set_invoice_status(&connection, &voided.to_string(), InvoiceStatus::Void, "2025-06-07 10:00:00")
+        .unwrap();
+
+    let drafts = database::get_invoices(&connection, Some("gus"), Some(InvoiceStatus::Draft)).unwrap();
+    assert_eq!(drafts.len(), 1);
+    let void = database::get_invoices(&connection, None, Some(InvoiceStatus::Void)).unwrap();
+    assert_eq!(void.len(), 1);
+    assert_eq!(void[0].id as i64, voided);
+    assert!(database::get_invoices(&connection, None, Some(InvoiceStatus::Paid)).unwrap().is_empty());
+}
+
+#[test]
+fn test_record_and_get_payments() {
+    let connection = setup();
+
+    database::new_client(&connection, "Hal", "hal", "hal@example.com", "55555", None).unwrap();
+    let first = database::new_invoice(&connection, "hal", "2025-06-06", None, None, 30, "AUD").unwrap() as i32;
+    let second = database::new_invoice(&connection, "hal", "2025-06-07", None, None, 30, "AUD").unwrap() as i32;
+
+    let amount = Money::new(5000, "AUD");
+    database::record_payment(&connection, first, "2025-07-02", &amount, "bank transfer", Some("R2"))
+        .unwrap();
+    database::record_payment(&connection, first, "2025-07-01", &amount, "cash", None).unwrap();
+    database::record_payment(&connection, second, "2025-07-03", &amount, "card", None).unwrap();
+
+    let payments = database::get_payments(&connection, Some(first)).unwrap();
+    assert_eq!(payments.len(), 2);
+    // Ordered by date received
+    assert_eq!(payments[0].method, "cash");
+    assert_eq!(payments[1].reference.as_deref(), Some("R2"));
+    assert_eq!(database::get_payments(&connection, None).unwrap().len(), 3);
+
+    let invoice = database::get_invoice(&connection, &first.to_string()).unwrap();
+    assert_eq!(invoice.payments.len(), 2);
+}
+
+#[test]
+fn test_new_invoice_due_date_from_payment_terms() {
+    let connection = setup();
+
+    database::new_client(&connection, "Ivy", "ivy", "ivy@example.com", "66666", Some(14)).unwrap();
+    database::new_client(&connection, "Jo", "jo", "jo@example.com", "77777", Some(0)).unwrap();
+    database::new_client(&connection, "Kim", "kim", "kim@example.com", "88888", None).unwrap();
+
+    database::new_invoice(&connection, "ivy", "2025-06-06", None, None, 30, "AUD").unwrap();
+    database::new_invoice(&connection, "jo", "2025-06-06", None, None, 30, "AUD").unwrap();
+    database::new_invoice(&connection, "kim", "2025-06-06", None, None, 30, "AUD").unwrap();
+    // An explicit due date wins over the client's terms
+    database::new_invoice(&connection, "ivy", "2025-06-06", Some("2025-06-09"), None, 30, "AUD").unwrap();
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    let due_dates: Vec<&str> = invoices.iter().map(|invoice| invoice.due_date.as_str()).collect();
+    assert_eq!(due_dates, vec!["2025-06-20", "2025-06-06", "2025-07-06", "2025-06-09"]);
+}
+
+#[test]
+fn test_new_invoice_uses_configured_defaults() {
+    let connection = setup();
+
+    database::new_client(&connection, "Lou", "lou", "lou@example.com", "99999", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "lou", "2025-06-06", None, None, 7, "NZD").unwrap();
+
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.due_date, "2025-06-13");
+    assert_eq!(invoice.currency, "NZD");
+}
+
+#[test]
+fn test_new_invoice_unknown_client() {
+    let connection = setup();
+
+    let result = database::new_invoice(&connection, "nobody", "2025-06-06", None, None, 30, "AUD");
+    assert!(matches!(result, Err(ClientError::ClientNotFound(nickname)) if nickname == "nobody"));
+
+    let result = database::get_invoice(&connection, "999");
+    assert!(matches!(result, Err(InvoiceError::InvoiceNotFound(id)) if id == "999"));
+}
+
+#[test]
+fn test_save_and_get_profile() {
+    let connection = setup();
+    assert_eq!(database::get_profile(&connection, None).unwrap(), None);
+
+    let mut profile = BusinessProfile {
+        name: "caleb".to_string(),
+        business_name: "Walker Consulting".to_string(),
+        abn: "51 824 753 556".to_string(),
+        address: "1 Example St\nBrisbane QLD 4000".to_string(),
+        bank_bsb: "123-456".to_string(),
+        ..Default::default()
+    };
+    database::save_profile(&connection, &profile).unwrap();
+    // The first profile becomes the default
+    let saved = database::get_profile(&connection, None).unwrap().unwrap();
+    assert!(saved.is_default);
+    assert_eq!(saved.business_name, "Walker Consulting");
+    assert_eq!(saved.address, "1 Example St\nBrisbane QLD 4000");
+
+    // Saving again under the same name updates rather than adding a profile
+    profile.payid = "0400000000".to_string();
+    database::save_profile(&connection, &profile).unwrap();
+    let profiles = database::get_profiles(&connection).unwrap();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].payid, "0400000000");
+
+    database::new_client(&connection, "Alice", "al", "alice@example.com", "12345", None).unwrap();
+    let invoice_id =
+        database::new_invoice(&connection, "al", "2025-06-06", None, Some(profiles[0].id), 30, "AUD").unwrap();
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.issuer, profiles[0]);
+}
+
+#[test]
+fn test_multiple_profiles() {
+    let connection = setup();
+
+    for (name, business_name) in [("caleb", "Walker Consulting"), ("sam", "Sam Smith")] {
+        let profile = BusinessProfile {
+            name: name.to_string(),
+            business_name: business_name.to_string(),
+            ..Default::default()
+        };
+        database::save_profile(&connection, &profile).unwrap();
+    }
+    assert_eq!(database::get_profile(&connection, None).unwrap().unwrap().name, "caleb");
+    assert_eq!(database::get_profile(&connection, Some("nobody")).unwrap(), None);
+
+    database::set_default_profile(&connection, "sam").unwrap();
+    let default = database::get_profile(&connection, None).unwrap().unwrap();
+    assert_eq!(default.business_name, "Sam Smith");
+    let result = database::set_default_profile(&connection, "nobody");
+    assert!(matches!(result, Err(ProfileError::ProfileNotFound(name)) if name == "nobody"));
+    // A failed change keeps the old default
+    assert_eq!(database::get_profile(&connection, None).unwrap().unwrap().name, "sam");
+}
+
+#[test]
+fn test_invoice_numbers_are_per_profile() {
+    let connection = setup();
+
+    database::new_client(&connection, "Alice", "al", "alice@example.com", "12345", None).unwrap();
+    let mut ids = Vec::new();
+    for name in ["caleb", "sam"] {
+        let profile = BusinessProfile {
+            name: name.to_string(),
+            business_name: name.to_string(),
+            ..Default::default()
+        };
+        database::save_profile(&connection, &profile).unwrap();
+        ids.push(database::get_profile(&connection, Some(name)).unwrap().unwrap().id);
+    }
+    let mut sam = database::get_number_sequence(&connection, Some(ids[1]), DocumentType::Invoice).unwrap();
+    sam.prefix = "SS".to_string();
+    sam.yearly_reset = false;
+    database::save_number_sequence(&connection, &sam).unwrap();
+
+    for profile_id in [ids[0], ids[0], ids[1], ids[0]] {
+        let invoice_id =
+            database::new_invoice(&connection, "al", "2026-01-06", None, Some(profile_id), 30, "AUD").unwrap();
+        database::set_invoice_status(
+            &connection,
+            &invoice_id.to_string(),
+            InvoiceStatus::Issued,
+            "2026-01-07 09:00:00",
+        )
+        .unwrap();
+    }
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    let numbers: Vec<Option<&str>> = invoices.iter().map(|invoice| invoice.number.as_deref()).collect();
+    assert_eq!(
+        numbers,
+        vec![Some("INV-2026-0001"), Some("INV-2026-0002"), Some("SS0001"), Some("INV-2026-0003")]
+    );
+}
+
+#[test]
+fn test_invoice_numbered_at_issue() {
+    let connection = setup();
+
+    database::new_client(&connection, "Lou", "lou", "lou@example.com", "12345", None).unwrap();
+    let first = database::new_invoice(&connection, "lou", "2025-12-30", None, None, 30, "AUD").unwrap().to_string();
+    let second = database::new_invoice(&connection, "lou", "2025-12-30", None, None, 30, "AUD").unwrap().to_string();
+    let third = database::new_invoice(&connection, "lou", "2025-12-31", None, None, 30, "AUD").unwrap().to_string();
+    assert_eq!(database::get_invoice(&connection, &first).unwrap().number, None);
+
+    // Numbers follow the order of issue, not creation, and restart each year
+    database::set_invoice_status(&connection, &second, InvoiceStatus::Issued, "2025-12-31 10:00:00").unwrap();
+    database::set_invoice_status(&connection, &first, InvoiceStatus::Issued, "2026-01-02 10:00:00").unwrap();
+    database::set_invoice_status(&connection, &third, InvoiceStatus::Issued, "2026-01-02 11:00:00").unwrap();
+
+    assert_eq!(database::get_invoice(&connection, &second).unwrap().number.as_deref(), Some("INV-2025-0001"));
+    assert_eq!(database::get_invoice(&connection, &first).unwrap().number.as_deref(), Some("INV-2026-0001"));
+    assert_eq!(database::get_invoice(&connection, &third).unwrap().number.as_deref(), Some("INV-2026-0002"));
+
+    // Later transitions keep the number
+    database::set_invoice_status(&connection, &first, InvoiceStatus::Void, "2026-01-03 10:00:00").unwrap();
+    assert_eq!(database::get_invoice(&connection, &first).unwrap().number.as_deref(), Some("INV-2026-0001"));
+}
+
+#[test]
+fn test_delete_issued_invoice_refused() {
+    let connection = setup();
+
+    database::new_client(&connection, "Max", "max", "max@example.com", "12345", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "max", "2025-06-06", None, None, 30, "AUD").unwrap().to_string();
+    database::set_invoice_status(&connection, &invoice_id, InvoiceStatus::Issued, "2025-06-06 10:00:00").unwrap();
+
+    let result = database::delete_invoice(&connection, &invoice_id);
+    assert!(matches!(result, Err(InvoiceError::NotDraft(InvoiceStatus::Issued))));
+    assert!(database::get_invoice(&connection, &invoice_id).is_ok());
+
+    let result = database::delete_invoice(&connection, "999");
+    assert!(matches!(result, Err(InvoiceError::InvoiceNotFound(_))));
+}
+
+#[test]
+fn test_edit_and_remove_invoice_items() {
+    let connection = setup();
+
+    database::new_client(&connection, "Ora", "ora", "ora@example.com", "12345", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "ora", "2026-03-01", None, None, 30, "AUD").unwrap();
+    let rate = Money::new(10000, "AUD");
+    let design = database::add_invoice_item(&connection, invoice_id, "Design", 2.0, &rate, "gst").unwrap();
+    let build = database::add_invoice_item(&connection, invoice_id, "Build", 1.0, &rate, "gst").unwrap();
+
+    database::update_invoice_item(&connection, invoice_id, design, "Design review", 3.0, &Money::new(12000, "AUD"), "none").unwrap();
+    database::remove_invoice_item(&connection, invoice_id, build).unwrap();
+
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.items.len(), 1);
+    assert_eq!(invoice.items[0].description, "Design review");
+    assert_eq!(invoice.items[0].hours, 3.0);
+    assert_eq!(invoice.items[0].amount, Money::new(36000, "AUD"));
+    assert_eq!(invoice.items[0].tax_rate.code, "none");
+
+    // Items belong to one invoice
+    let other = database::new_invoice(&connection, "ora", "2026-03-01", None, None, 30, "AUD").unwrap();
+    let result = database::remove_invoice_item(&connection, other, design);
+    assert!(matches!(result, Err(InvoiceError::ItemNotFound { .. })));
+}
+
+#[test]
+fn test_items_locked_once_issued() {
+    let connection = setup();
+
+    database::new_client(&connection, "Pip", "pip", "pip@example.com", "12345", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "pip", "2026-03-01", None, None, 30, "AUD").unwrap();
+    let rate = Money::new(10000, "AUD");
+    let item_id = database::add_invoice_item(&connection, invoice_id, "Design", 2.0, &rate, "gst").unwrap();
+    database::set_invoice_status(&connection, &invoice_id.to_string(), InvoiceStatus::Issued, "2026-03-01 09:00:00").unwrap();
+
+    let added = database::add_invoice_item(&connection, invoice_id, "Extra", 1.0, &rate, "gst");
+    assert!(matches!(added, Err(InvoiceError::NotDraft(InvoiceStatus::Issued))));
+    let edited = database::update_invoice_item(&connection, invoice_id, item_id, "Cheaper", 1.0, &rate, "gst");
+    assert!(matches!(edited, Err(InvoiceError::NotDraft(InvoiceStatus::Issued))));
+    let removed = database::remove_invoice_item(&connection, invoice_id, item_id);
+    assert!(matches!(removed, Err(InvoiceError::NotDraft(InvoiceStatus::Issued))));
+
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.items.len(), 1);
+    assert_eq!(invoice.items[0].description, "Design");
+}
+
+#[test]
+fn test_new_credit_note() {
+    let connection = setup();
+
+    database::new_client(&connection, "Ned", "ned", "ned@example.com", "12345", None).unwrap();
+    let invoice_id = database::new_invoice(&connection, "ned", "2026-02-01", None, None, 30, "AUD").unwrap();
+    let rate = Money::new(10000, "AUD");
+    database::add_invoice_item(&connection, invoice_id, "Design", 3.0, &rate, "gst").unwrap();
+    database::set_invoice_status(&connection, &invoice_id.to_string(), InvoiceStatus::Issued, "2026-02-01 09:00:00").unwrap();
+
+    let item = NewItem {
+        description: "Design (overbilled)".to_string(),
+        hours: 1.0,
+        rate,
+        tax_code: "gst".to_string(),
+    };
+    for _ in 0..2 {
+        database::new_credit_note(&connection, invoice_id as i32, "2026-02-10", "Overbilled", std::slice::from_ref(&item))
+            .unwrap();
+    }
+
+    // Credit notes have their own sequence, separate from invoices
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.number.as_deref(), Some("INV-2026-0001"));
+    let numbers: Vec<&str> = invoice.credit_notes.iter().map(|credit_note| credit_note.number.as_str()).collect();
+    assert_eq!(numbers, vec!["CN-2026-0001", "CN-2026-0002"]);
+    // Numbers are unique, even when the sequence is rewound by hand
+    let second = invoice.credit_notes[1].id;
+    assert!(connection.execute("UPDATE credit_note SET number = 'CN-2026-0001' WHERE id = ?1", [second]).is_err());
+
+    let credit_note = database::get_credit_note(&connection, &invoice.credit_notes[0].id.to_string()).unwrap();
+    assert_eq!(credit_note.reason, "Overbilled");
+    assert_eq!(credit_note.items.len(), 1);
+    assert_eq!(credit_note.items[0].amount, Money::new(10000, "AUD"));
+    assert_eq!(credit_note.items[0].tax_rate.basis_points, 1000);
+    assert!(database::get_credit_note(&connection, "999").is_err());
+}
+
+#[test]
+fn test_update_client() {
+    let connection = setup();
+
+    database::new_client(&connection, "Olive", "liv", "olive@example.com", "0412345678", None).unwrap();
+    database::update_client(
+        &connection,
+        "liv",
+        "Olive Oyl",
+        "olive",
+        "olive@oyl.com",
+        "0498765432",
+        Some(14),
+        "AU",
+    )
+    .unwrap();
+
+    assert!(database::get_client(&connection, "liv").unwrap().is_none());
+    let client = database::get_client(&connection, "olive").unwrap().unwrap();
+    assert_eq!(client.name, "Olive Oyl");
+    assert_eq!(client.email, "olive@oyl.com");
+    assert_eq!(client.phone_number, "0498765432");
+    assert_eq!(client.payment_terms_days, Some(14));
+}
+
+#[test]
+fn test_update_client_validates() {
+    let connection = setup();
+
+    database::new_client(&connection, "Pat", "pat", "pat@example.com", "0412345678", None).unwrap();
+    let result = database::update_client(&connection, "pat", "Pat", "pat", "not an email", "0412345678", None, "AU");
+    assert!(matches!(result, Err(ClientError::InvalidEmail(_))));
+    let result = database::update_client(&connection, "pat", "Pat", "pat", "pat@example.com", "phone", None, "AU");
+    assert!(matches!(result, Err(ClientError::InvalidPhone(_))));
+    let result = database::update_client(&connection, "nobody", "Pat", "pat", "pat@example.com", "0412345678", None, "AU");
+    assert!(matches!(result, Err(ClientError::ClientNotFound(_))));
+
+    // Nicknames stay unique
+    let result = database::new_client(&connection, "Patricia", "pat", "tricia@example.com", "0412345678", None);
+    assert!(matches!(result, Err(ClientError::DuplicateNickname(nickname)) if nickname == "pat"));
+    database::new_client(&connection, "Patricia", "tricia", "tricia@example.com", "0412345678", None).unwrap();
+    let result = database::update_client(&connection, "tricia", "Patricia", "pat", "tricia@example.com", "0412345678", None, "AU");
+    assert!(matches!(result, Err(ClientError::DuplicateNickname(_))));
+
+    // Nothing changed
+    let client = database::get_client(&connection, "pat").unwrap().unwrap();
+    assert_eq!(client.email, "pat@example.com");
+}
+
+#[test]
+fn test_delete_client_with_invoices_refused() {
+    let connection = setup();
+
+    database::new_client(&connection, "Quinn", "quinn", "quinn@example.com", "12345", None).unwrap();
+    database::new_invoice(&connection, "quinn", "2025-06-06", None, None, 30, "AUD").unwrap();
+
+    let result = database::delete_client(&connection, "quinn");
+    assert!(matches!(result, Err(ClientError::HasInvoices(_))));
+    assert!(database::get_client(&connection, "quinn").unwrap().is_some());
+}
+
+#[test]
+fn test_open_db_creates_file_and_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ledgers").join("walker.db");
+
+    let (connection, created) = database::open_db(&path).unwrap();
+    assert!(created);
+    assert!(path.exists());
+    database::new_client(&connection, "Quinn", "quinn", "quinn@example.com", "12345", None).unwrap();
+    drop(connection);
+
+    // Opening again keeps the data
+    let (connection, created) = database::open_db(&path).unwrap();
+    assert!(!created);
+    assert_eq!(database::get_clients(&connection).unwrap().len(), 1);
+}
+
+#[test]
+fn test_set_client_template() {
+    let connection = setup();
+
+    database::new_client(&connection, "Quinn", "quinn", "quinn@example.com", "0412345678", None).unwrap();
+    assert_eq!(database::get_client(&connection, "quinn").unwrap().unwrap().template, None);
+
+    database::set_client_template(&connection, "quinn", Some("minimal")).unwrap();
+    let client = database::get_client(&connection, "quinn").unwrap().unwrap();
+    assert_eq!(client.template.as_deref(), Some("minimal"));
+
+    // Invoices carry the client's template through to generation
+    let invoice_id = database::new_invoice(&connection, "quinn", "2026-01-05", None, None, 30, "AUD").unwrap();
+    let invoice = database::get_invoice(&connection, &invoice_id.to_string()).unwrap();
+    assert_eq!(invoice.client_template.as_deref(), Some("minimal"));
+
+    database::set_client_template(&connection, "quinn", None).unwrap();
+    assert_eq!(database::get_client(&connection, "quinn").unwrap().unwrap().template, None);
+
+    assert!(matches!(
+        database::set_client_template(&connection, "nobody", Some("minimal")),
+        Err(ClientError::ClientNotFound(_))
+    ));
+}
+
+#[test]
+fn test_invoice_numbers_unique_without_profile() {
+    let connection = setup();
+
+    database::new_client(&connection, "Val", "val", "val@example.com", "12345", None).unwrap();
+    let first = database::new_invoice(&connection, "val", "2026-01-05", None, None, 30, "AUD").unwrap();
+    let second = database::new_invoice(&connection, "val", "2026-01-05", None, None, 30, "AUD").unwrap();
+    connection.execute("UPDATE invoice SET number = 'INV-2026-0001' WHERE id = ?1", [first]).unwrap();
+    // Invoices without a profile are covered by the unique index too
+    assert!(connection.execute("UPDATE invoice SET number = 'INV-2026-0001' WHERE id = ?1", [second]).is_err());
+}
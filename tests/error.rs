@@ -0,0 +1,86 @@
+use clinv::database::{ClientError, InvoiceError, ProfileError};
+use clinv::error::{exit_code, Error};
+use clinv::items::ItemError;
+use clinv::models::InvoiceStatus;
+use clinv::money::MoneyError;
+
+#[test]
+fn test_invoice_errors_convert_by_kind() {
+    assert!(matches!(
+        Error::from(InvoiceError::InvoiceNotFound("3".to_string())),
+        Error::InvoiceNotFound(id) if id == "3"
+    ));
+    assert!(matches!(
+        Error::from(InvoiceError::NotDraft(InvoiceStatus::Sent)),
+        Error::Validation(_)
+    ));
+    assert!(matches!(
+        Error::from(InvoiceError::ItemNotFound {
+            invoice_id: "3".to_string(),
+            item_id: "9".to_string(),
+        }),
+        Error::NotFound(_)
+    ));
+    assert!(matches!(
+        Error::from(InvoiceError::Sqlite(rusqlite::Error::QueryReturnedNoRows)),
+        Error::Storage(_)
+    ));
+}
+
+#[test]
+fn test_other_errors_convert_by_kind() {
+    assert!(matches!(
+        Error::from(ClientError::ClientNotFound("al".to_string())),
+        Error::ClientNotFound(nickname) if nickname == "al"
+    ));
+    assert!(matches!(
+        Error::from(ClientError::InvalidEmail("nope".to_string())),
+        Error::Validation(_)
+    ));
+    let error = Error::from(ClientError::DuplicateNickname("al".to_string()));
+    assert_eq!(error.exit_code(), exit_code::VALIDATION);
+    let error = Error::from(ProfileError::ProfileNotFound("sam".to_string()));
+    assert!(matches!(error, Error::NotFound(_)));
+    assert_eq!(error.exit_code(), exit_code::NOT_FOUND);
+    assert!(matches!(Error::from(MoneyError::Empty), Error::Validation(_)));
+    assert!(matches!(
+        Error::from(ItemError::Invalid {
+            item: 1,
+            message: "bad".to_string(),
+        }),
+        Error::Validation(message) if message == "item 1: bad"
+    ));
+}
+
+#[test]
+fn test_not_found_messages_suggest_a_command() {
+    let message = Error::ClientNotFound("al".to_string()).to_string();
+    assert!(message.contains("al"));
+    assert!(message.contains("clinv list-clients"));
+
+    let message = Error::InvoiceNotFound("3".to_string()).to_string();
+    assert!(message.contains("clinv list-invoices"));
+}
+
+#[test]
+fn test_exit_codes() {
+    let rusqlite_error = || rusqlite::Error::QueryReturnedNoRows;
+    assert_eq!(Error::Usage("huh".to_string()).exit_code(), exit_code::USAGE);
+    assert_eq!(Error::ClientNotFound("al".to_string()).exit_code(), exit_code::NOT_FOUND);
+    assert_eq!(Error::InvoiceNotFound("3".to_string()).exit_code(), exit_code::NOT_FOUND);
+    assert_eq!(Error::NotFound("no profile".to_string()).exit_code(), exit_code::NOT_FOUND);
+    assert_eq!(Error::Validation("bad".to_string()).exit_code(), exit_code::VALIDATION);
+    assert_eq!(Error::Template("missing".to_string()).exit_code(), exit_code::PDF);
+    assert_eq!(Error::Pdf("failed".to_string()).exit_code(), exit_code::PDF);
+    assert_eq!(Error::Storage(rusqlite_error()).exit_code(), exit_code::STORAGE);
+
+    // Every failure is distinguishable from success
+    let codes = [
+        exit_code::USAGE,
+        exit_code::NOT_FOUND,
+        exit_code::VALIDATION,
+        exit_code::PDF,
+        exit_code::STORAGE,
+    ];
+    assert!(!codes.contains(&exit_code::SUCCESS));
+}
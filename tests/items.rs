@@ -0,0 +1,86 @@
+use clinv::database::init_db;
+use clinv::items::{self, ItemError};
+use clinv::money::Money;
+use rusqlite::Connection;
+use std::io::Write;
+
+fn write_file(suffix: &str, contents: &str) -> tempfile::NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file
+}
+
+#[test]
+fn test_parse_item_spec() {
+    let item = items::parse_item_spec("Design work; 2.5; 120.00", "AUD", 1).unwrap();
+    assert_eq!(item.description, "Design work");
+    assert_eq!(item.hours, 2.5);
+    assert_eq!(item.rate, Money::new(12000, "AUD"));
+    assert_eq!(item.tax_code, "gst");
+
+    let item = items::parse_item_spec("Hosting;1;25;gst-free", "AUD", 1).unwrap();
+    assert_eq!(item.tax_code, "gst-free");
+}
+
+#[test]
+fn test_parse_item_spec_invalid() {
+    for spec in ["Design;2", "Design;two;120", "Design;2;lots", ";2;120", "Design;0;120", "a;1;2;gst;extra"] {
+        let result = items::parse_item_spec(spec, "AUD", 3);
+        assert!(matches!(result, Err(ItemError::Invalid { item: 3, .. })), "{}", spec);
+    }
+}
+
+#[test]
+fn test_read_items_file_formats() {
+    let json = write_file(
+        ".json",
+        r#"[{"description": "Design", "hours": 2, "rate": "120.00"},
+            {"description": "Hosting", "hours": 1, "rate": 25, "tax_code": "gst-free"}]"#,
+    );
+    let toml = write_file(
+        ".toml",
+        "[[item]]\ndescription = \"Design\"\nhours = 2.0\nrate = \"120.00\"\n\n\
+         [[item]]\ndescription = \"Hosting\"\nhours = 1.0\nrate = 25\ntax_code = \"gst-free\"\n",
+    );
+    let csv = write_file(
+        ".csv",
+        "description,hours,rate,tax_code\nDesign,2,120.00,\nHosting,1,25,gst-free\n",
+    );
+
+    for file in [&json, &toml, &csv] {
+        let items = items::read_items_file(file.path(), "AUD").unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "Design");
+        assert_eq!(items[0].rate, Money::new(12000, "AUD"));
+        assert_eq!(items[0].tax_code, "gst");
+        assert_eq!(items[1].rate, Money::new(2500, "AUD"));
+        assert_eq!(items[1].tax_code, "gst-free");
+    }
+}
+
+#[test]
+fn test_read_items_file_errors() {
+    let bad_rate = write_file(".csv", "description,hours,rate\nDesign,2,120\nHosting,1,cheap\n");
+    let result = items::read_items_file(bad_rate.path(), "AUD");
+    assert!(matches!(result, Err(ItemError::Invalid { item: 2, .. })));
+
+    let unknown = write_file(".xml", "<items/>");
+    let result = items::read_items_file(unknown.path(), "AUD");
+    assert!(matches!(result, Err(ItemError::UnknownFormat(_))));
+
+    let empty = write_file(".json", "[]");
+    let result = items::read_items_file(empty.path(), "AUD");
+    assert!(matches!(result, Err(ItemError::Empty(_))));
+}
+
+#[test]
+fn test_check_tax_codes() {
+    let connection = Connection::open_in_memory().unwrap();
+    init_db(&connection).unwrap();
+
+    let good = items::parse_item_spec("Design;1;100;gst", "AUD", 1).unwrap();
+    let bad = items::parse_item_spec("Design;1;100;vat", "AUD", 2).unwrap();
+    assert!(items::check_tax_codes(&connection, std::slice::from_ref(&good)).is_ok());
+    let result = items::check_tax_codes(&connection, &[good, bad]);
+    assert!(matches!(result, Err(ItemError::Invalid { item: 2, .. })));
+}
@@ -0,0 +1,183 @@
+use clinv::database::{self, init_db};
+use clinv::{balance, reports, tax};
+use clinv::migrations::{self, MigrationError};
+use clinv::models::InvoiceStatus;
+use clinv::money::Money;
+use rusqlite::Connection;
+
+/// Builds a database exactly as clinv created it before schema versioning
+fn baseline_db() -> Connection {
+    let connection = Connection::open_in_memory().unwrap();
+    connection
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS client (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                nickname TEXT UNIQUE NOT NULL,
+                email TEXT NOT NULL,
+                phone_number TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS invoice (
+                id INTEGER PRIMARY KEY,
+                client_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                FOREIGN KEY (client_id) REFERENCES client(id)
+            );
+            CREATE TABLE IF NOT EXISTS invoice_item (
+                id INTEGER PRIMARY KEY,
+                invoice_id INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                hours FLOAT NOT NULL,
+                rate FLOAT NOT NULL,
+                amount FLOAT NOT NULL,
+                FOREIGN KEY (invoice_id) REFERENCES invoice(id)
+            );
+            INSERT INTO client (name, nickname, email, phone_number)
+                VALUES ('Alice', 'al', 'alice@example.com', '0412345678');
+            INSERT INTO invoice (client_id, date) VALUES (1, '2025-06-06');
+            INSERT INTO invoice_item (invoice_id, description, hours, rate, amount)
+                VALUES (1, 'Design', 2.5, 120.0, 300.0);",
+        )
+        .unwrap();
+    connection
+}
+
+#[test]
+fn test_fresh_database_is_at_latest_version() {
+    let connection = Connection::open_in_memory().unwrap();
+    init_db(&connection).unwrap();
+    assert_eq!(
+        migrations::schema_version(&connection).unwrap(),
+        migrations::latest_version()
+    );
+}
+
+#[test]
+fn test_baseline_database_upgrades_to_latest() {
+    let connection = baseline_db();
+    assert_eq!(migrations::schema_version(&connection).unwrap(), 0);
+
+    init_db(&connection).unwrap();
+    assert_eq!(
+        migrations::schema_version(&connection).unwrap(),
+        migrations::latest_version()
+    );
+
+    // Existing data survives the upgrade
+    let clients = database::get_clients(&connection).unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0].nickname, "al");
+    let invoices = database::get_invoices(&connection, Some("al"), None).unwrap();
+    assert_eq!(invoices.len(), 1);
+    assert_eq!(invoices[0].items.len(), 1);
+    assert_eq!(invoices[0].items[0].description, "Design");
+}
+
+#[test]
+fn test_float_money_converted_to_minor_units() {
+    let connection = baseline_db();
+    connection
+        .execute_batch(
+            "INSERT INTO invoice_item (invoice_id, description, hours, rate, amount)
+                VALUES (1, 'Odd rate', 3, 33.335, 100.005);",
+        )
+        .unwrap();
+    init_db(&connection).unwrap();
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    let items = &invoices[0].items;
+    assert_eq!(invoices[0].currency, "AUD");
+    assert_eq!(items[0].rate, Money::new(12000, "AUD"));
+    assert_eq!(items[0].amount, Money::new(30000, "AUD"));
+    // Rounded half away from zero
+    assert_eq!(items[1].rate, Money::new(3334, "AUD"));
+    assert_eq!(items[1].amount, Money::new(10001, "AUD"));
+}
+
+#[test]
+fn test_existing_items_are_untaxed() {
+    let connection = baseline_db();
+    init_db(&connection).unwrap();
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    assert_eq!(invoices[0].items[0].tax_rate.code, "none");
+    assert_eq!(invoices[0].items[0].tax_rate.basis_points, 0);
+}
+
+#[test]
+fn test_existing_invoices_marked_paid() {
+    let connection = baseline_db();
+    init_db(&connection).unwrap();
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    assert_eq!(invoices[0].status, InvoiceStatus::Paid);
+    assert_eq!(invoices[0].issued_at.as_deref(), Some("2025-06-06"));
+    assert_eq!(invoices[0].paid_at.as_deref(), Some("2025-06-06"));
+
+    // Settled by a payment for the full amount, so nothing shows as owed
+    let payments = database::get_payments(&connection, Some(invoices[0].id)).unwrap();
+    assert_eq!(payments.len(), 1);
+    let totals = tax::compute_totals(&invoices[0].items, &invoices[0].currency);
+    let balance = balance::compute_balance(&totals, &payments, &[]);
+    assert!(balance.is_settled());
+    assert_eq!(balance.amount_paid, Money::new(30000, "AUD"));
+    let as_of = chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+    assert!(reports::aging_report(&connection, as_of).unwrap().rows.is_empty());
+}
+
+#[test]
+fn test_existing_invoices_due_net_30() {
+    let connection = baseline_db();
+    init_db(&connection).unwrap();
+
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    assert_eq!(invoices[0].due_date, "2025-07-06");
+}
+
+#[test]
+fn test_existing_invoices_keep_their_number() {
+    let connection = baseline_db();
+    init_db(&connection).unwrap();
+
+    // The number previously shown on the PDF
+    let invoices = database::get_invoices(&connection, None, None).unwrap();
+    assert_eq!(invoices[0].sequence_number, Some(1));
+    assert_eq!(invoices[0].number.as_deref(), Some("INV-2025-06-06-1"));
+    // No profile existed to issue them
+    assert_eq!(invoices[0].profile_id, None);
+}
+
+#[test]
+fn test_migrate_is_idempotent() {
+    let connection = baseline_db();
+    init_db(&connection).unwrap();
+    init_db(&connection).unwrap();
+    assert_eq!(
+        migrations::schema_version(&connection).unwrap(),
+        migrations::latest_version()
+    );
+}
+
+#[test]
+fn test_refuses_newer_database() {
+    let connection = Connection::open_in_memory().unwrap();
+    let newer = migrations::latest_version() + 1;
+    connection
+        .execute_batch(&format!("PRAGMA user_version = {}", newer))
+        .unwrap();
+
+    match init_db(&connection) {
+        Err(MigrationError::NewerSchema { found, supported }) => {
+            assert_eq!(found, newer);
+            assert_eq!(supported, migrations::latest_version());
+        }
+        other => panic!("Expected NewerSchema error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_migration_versions_are_ordered() {
+    let versions: Vec<i64> = migrations::MIGRATIONS.iter().map(|m| m.version).collect();
+    let expected: Vec<i64> = (1..=versions.len() as i64).collect();
+    assert_eq!(versions, expected);
+}
@@ -1,4 +1,9 @@
-use clinv::models::{Client, Invoice, InvoiceForPdf, InvoiceItem};
+use clinv::models::{Client, DocumentType, Invoice, InvoiceItem, InvoiceStatus, NumberSequence, Payment};
+use clinv::money::Money;
+
+mod common;
+
+use common::gst;
 
 #[test]
 fn test_client_struct() {
@@ -8,6 +13,8 @@ fn test_client_struct() {
         nickname: "johnny".to_string(),
         email: "john@example.com".to_string(),
         phone_number: "123456789".to_string(),
+        payment_terms_days: Some(14),
+        template: None,
     };
 
     assert_eq!(client.id, 1);
@@ -15,6 +22,7 @@ fn test_client_struct() {
     assert_eq!(client.nickname, "johnny");
     assert_eq!(client.email, "john@example.com");
     assert_eq!(client.phone_number, "123456789");
+    assert_eq!(client.payment_terms_days, Some(14));
 }
 
 #[test]
@@ -23,13 +31,24 @@ fn test_invoice_struct() {
         id: 2,
         description: "Design".to_string(),
         hours: 2.0,
-        rate: 150.0,
-        amount: 300.0,
+        rate: Money::new(15000, "AUD"),
+        amount: Money::new(30000, "AUD"),
+        tax_rate: gst(),
     };
     let invoice = Invoice {
         id: 5,
         client_id: 1,
+        profile_id: None,
+        sequence_number: None,
+        number: None,
         date: "2025-01-01".to_string(),
+        due_date: "2025-01-31".to_string(),
+        currency: "AUD".to_string(),
+        status: InvoiceStatus::Draft,
+        issued_at: None,
+        sent_at: None,
+        paid_at: None,
+        voided_at: None,
         items: vec![item],
     };
 
@@ -37,34 +56,38 @@ fn test_invoice_struct() {
     assert_eq!(invoice.client_id, 1);
     assert_eq!(invoice.date, "2025-01-01");
     assert_eq!(invoice.items.len(), 1);
-    assert_eq!(invoice.items[0].amount, 300.0);
+    assert_eq!(invoice.items[0].amount, Money::new(30000, "AUD"));
 }
 
 #[test]
 fn test_invoice_for_pdf_struct() {
-    let items = vec![InvoiceItem {
-        id: 3,
-        description: "Dev Work".to_string(),
-        hours: 3.0,
-        rate: 200.0,
-        amount: 600.0,
-    }];
-    let pdf = InvoiceForPdf {
-        id: 7,
-        client_name: "Jane Smith".to_string(),
-        client_email: "jane@smith.com".to_string(),
-        client_phone_number: "999888777".to_string(),
-        date: "2025-06-06".to_string(),
-        items,
-    };
+    let pdf = common::invoice(vec![common::item("Dev Work", 3.0, 20000, gst())]);
 
     assert_eq!(pdf.id, 7);
-    assert_eq!(pdf.client_name, "Jane Smith");
-    assert_eq!(pdf.client_email, "jane@smith.com");
-    assert_eq!(pdf.client_phone_number, "999888777");
-    assert_eq!(pdf.date, "2025-06-06");
+    assert_eq!(pdf.client_name, "Alice");
+    assert_eq!(pdf.client_email, "alice@example.com");
+    assert_eq!(pdf.client_phone_number, "0412345678");
+    assert_eq!(pdf.date, "2026-03-02");
     assert_eq!(pdf.items.len(), 1);
     assert_eq!(pdf.items[0].description, "Dev Work");
+    assert_eq!(pdf.items[0].amount, Money::new(60000, "AUD"));
+    assert!(pdf.payments.is_empty());
+}
+
+#[test]
+fn test_payment_struct() {
+    let payment = Payment {
+        id: 4,
+        invoice_id: 7,
+        date: "2025-07-01".to_string(),
+        amount: Money::new(25000, "AUD"),
+        method: "bank transfer".to_string(),
+        reference: Some("REF123".to_string()),
+    };
+
+    assert_eq!(payment.invoice_id, 7);
+    assert_eq!(payment.amount, Money::new(25000, "AUD"));
+    assert_eq!(payment.reference.as_deref(), Some("REF123"));
 }
 
 #[test]
@@ -73,13 +96,102 @@ fn test_invoice_item_struct() {
         id: 10,
         description: "Consulting".to_string(),
         hours: 5.0,
-        rate: 100.0,
-        amount: 500.0,
+        rate: Money::new(10000, "AUD"),
+        amount: Money::new(50000, "AUD"),
+        tax_rate: gst(),
     };
 
     assert_eq!(item.id, 10);
     assert_eq!(item.description, "Consulting");
     assert_eq!(item.hours, 5.0);
-    assert_eq!(item.rate, 100.0);
-    assert_eq!(item.amount, 500.0);
+    assert_eq!(item.rate, Money::new(10000, "AUD"));
+    assert_eq!(item.amount, Money::new(50000, "AUD"));
+}
+
+#[test]
+fn test_tax_rate_struct() {
+    let rate = gst();
+
+    assert_eq!(rate.code, "gst");
+    assert_eq!(rate.name, "GST");
+    assert_eq!(rate.basis_points, 1000);
+}
+
+#[test]
+fn test_invoice_status_transitions() {
+    use InvoiceStatus::*;
+
+    assert!(Draft.can_transition_to(Issued));
+    assert!(Issued.can_transition_to(Sent));
+    assert!(Sent.can_transition_to(Paid));
+    for status in [Draft, Issued, Sent] {
+        assert!(status.can_transition_to(Void));
+    }
+
+    assert!(!Draft.can_transition_to(Paid));
+    assert!(!Issued.can_transition_to(Draft));
+    assert!(!Paid.can_transition_to(Void));
+    assert!(!Void.can_transition_to(Issued));
+}
+
+#[test]
+fn test_invoice_status_round_trip() {
+    for status in [
+        InvoiceStatus::Draft,
+        InvoiceStatus::Issued,
+        InvoiceStatus::Sent,
+        InvoiceStatus::Paid,
+        InvoiceStatus::Void,
+    ] {
+        assert_eq!(status.as_str().parse::<InvoiceStatus>(), Ok(status));
+    }
+    assert_eq!("PAID".parse::<InvoiceStatus>(), Ok(InvoiceStatus::Paid));
+    assert!("overdue".parse::<InvoiceStatus>().is_err());
+}
+
+#[test]
+fn test_number_sequence_format() {
+    let mut sequence = NumberSequence::new(None, DocumentType::Invoice);
+    assert_eq!(sequence.format(2026, 42), "INV-2026-0042");
+    assert_eq!(sequence.format(2026, 12345), "INV-2026-12345");
+
+    sequence.yearly_reset = false;
+    sequence.prefix = "CW".to_string();
+    sequence.padding = 6;
+    assert_eq!(sequence.format(2026, 42), "CW000042");
+}
+
+#[test]
+fn test_number_sequence_yearly_reset() {
+    let mut sequence = NumberSequence::new(None, DocumentType::Invoice);
+    assert_eq!(sequence.peek(2026), 1);
+
+    sequence.year = Some(2026);
+    sequence.next_number = 8;
+    assert_eq!(sequence.peek(2026), 8);
+    assert_eq!(sequence.peek(2027), 1);
+
+    sequence.yearly_reset = false;
+    assert_eq!(sequence.peek(2027), 8);
+}
+
+#[test]
+fn test_credit_note_numbering_defaults() {
+    let sequence = NumberSequence::new(Some(1), DocumentType::CreditNote);
+    assert_eq!(sequence.format(2026, 3), "CN-2026-0003");
+    assert_eq!("credit-note".parse::<DocumentType>(), Ok(DocumentType::CreditNote));
+    assert_eq!(DocumentType::CreditNote.to_string(), "credit-note");
+    assert!("receipt".parse::<DocumentType>().is_err());
+}
+
+#[test]
+fn test_number_sequence_parse() {
+    let mut sequence = NumberSequence::new(None, DocumentType::Invoice);
+    assert_eq!(sequence.parse(2026, "INV-2026-0042"), Some(42));
+    assert_eq!(sequence.parse(2025, "INV-2026-0042"), None);
+    assert_eq!(sequence.parse(2026, "CN-2026-0042"), None);
+
+    sequence.yearly_reset = false;
+    assert_eq!(sequence.parse(2026, "INV-0007"), Some(7));
+    assert_eq!(sequence.parse(2026, "INV-2026-0042"), None);
 }
@@ -0,0 +1,84 @@
+use clinv::money::{is_supported_currency, Money, MoneyError};
+
+fn aud(minor_units: i64) -> Money {
+    Money::new(minor_units, "AUD")
+}
+
+#[test]
+fn test_parse_whole_and_decimal_amounts() {
+    assert_eq!(Money::parse("120", "AUD").unwrap(), aud(12000));
+    assert_eq!(Money::parse("99.5", "AUD").unwrap(), aud(9950));
+    assert_eq!(Money::parse("0.07", "AUD").unwrap(), aud(7));
+    assert_eq!(Money::parse(".5", "AUD").unwrap(), aud(50));
+    assert_eq!(Money::parse("$1,200.00", "AUD").unwrap(), aud(120000));
+    assert_eq!(Money::parse("-12.34", "AUD").unwrap(), aud(-1234));
+}
+
+#[test]
+fn test_parse_rounds_half_away_from_zero() {
+    assert_eq!(Money::parse("0.125", "AUD").unwrap(), aud(13));
+    assert_eq!(Money::parse("0.124", "AUD").unwrap(), aud(12));
+    assert_eq!(Money::parse("-0.125", "AUD").unwrap(), aud(-13));
+    assert_eq!(Money::parse("1.995", "AUD").unwrap(), aud(200));
+}
+
+#[test]
+fn test_parse_rejects_invalid_input() {
+    assert_eq!(Money::parse("", "AUD"), Err(MoneyError::Empty));
+    assert!(matches!(
+        Money::parse("abc", "AUD"),
+        Err(MoneyError::Invalid(_))
+    ));
+    assert!(matches!(
+        Money::parse("1.2.3", "AUD"),
+        Err(MoneyError::Invalid(_))
+    ));
+    assert!(matches!(
+        Money::parse(".", "AUD"),
+        Err(MoneyError::Invalid(_))
+    ));
+    assert_eq!(
+        Money::parse("99999999999999999999", "AUD"),
+        Err(MoneyError::Overflow)
+    );
+}
+
+#[test]
+fn test_times_rounds_to_nearest_minor_unit() {
+    assert_eq!(aud(15000).times(2.0), aud(30000));
+    assert_eq!(aud(3333).times(1.5), aud(5000)); // 49.995 -> 50.00
+    assert_eq!(aud(1005).times(0.1), aud(101)); // 1.005 -> 1.01, no float drift
+    assert_eq!(aud(-3333).times(1.5), aud(-5000));
+}
+
+#[test]
+fn test_add_and_display() {
+    let total = aud(10) + aud(1990);
+    assert_eq!(total, aud(2000));
+    assert_eq!(total.to_string(), "20.00");
+    assert_eq!(aud(5).to_string(), "0.05");
+    assert_eq!(aud(-1234).to_string(), "-12.34");
+}
+
+#[test]
+fn test_sub() {
+    assert_eq!(aud(2000) - aud(1990), aud(10));
+    assert_eq!(aud(1000) - aud(2500), aud(-1500));
+}
+
+#[test]
+#[should_panic]
+fn test_add_mismatched_currencies_panics() {
+    let _ = aud(100) + Money::new(100, "NZD");
+}
+
+#[test]
+fn test_is_supported_currency() {
+    assert!(is_supported_currency("AUD"));
+    assert!(is_supported_currency("EUR"));
+    // Amounts are always kept in hundredths
+    assert!(!is_supported_currency("JPY"));
+    assert!(!is_supported_currency("BHD"));
+    assert!(!is_supported_currency("aud"));
+    assert!(!is_supported_currency("DOLLARS"));
+}
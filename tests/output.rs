@@ -0,0 +1,149 @@
+use clinv::balance;
+use clinv::cli::OutputFormat;
+use clinv::database::{self, init_db};
+use clinv::models::{InvoiceStatus, NumberSequence, DocumentType};
+use clinv::money::Money;
+use clinv::output::{self, InvoiceListing};
+use clinv::tax;
+use rusqlite::Connection;
+
+fn setup() -> Connection {
+    let connection = Connection::open_in_memory().unwrap();
+    init_db(&connection).unwrap();
+    database::new_client(&connection, "Alice Smith", "al", "al@example.com", "0412345678", Some(14)).unwrap();
+    connection
+}
+
+fn listings(connection: &Connection) -> Vec<InvoiceListing> {
+    database::get_invoices(connection, None, None)
+        .unwrap()
+        .into_iter()
+        .map(|invoice| {
+            let totals = tax::compute_totals(&invoice.items, &invoice.currency);
+            let payments = database::get_payments(connection, Some(invoice.id)).unwrap();
+            let balance = balance::compute_balance(&totals, &payments, &[]);
+            InvoiceListing { invoice, balance }
+        })
+        .collect()
+}
+
+#[test]
+fn test_write_clients_formats() {
+    let connection = setup();
+    let clients = database::get_clients(&connection).unwrap();
+
+    let mut csv = Vec::new();
+    output::write_clients(&clients, 30, OutputFormat::Csv, &mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "id,name,nickname,email,phone_number,payment_terms_days,template\n\
+         1,Alice Smith,al,al@example.com,0412345678,14,\n"
+    );
+
+    let mut json = Vec::new();
+    output::write_clients(&clients, 30, OutputFormat::Json, &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json[0]["nickname"], "al");
+    assert_eq!(json[0]["payment_terms_days"], 14);
+
+    let mut table = Vec::new();
+    output::write_clients(&clients, 30, OutputFormat::Table, &mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.contains("nickname: al"));
+    assert!(table.contains("payment terms: Net 14"));
+}
+
+#[test]
+fn test_write_invoices_formats() {
+    let connection = setup();
+    let invoice_id = database::new_invoice(&connection, "al", "2026-05-01", None, None, 30, "AUD").unwrap();
+    database::add_invoice_item(&connection, invoice_id, "Design", 2.0, &Money::new(10000, "AUD"), "gst").unwrap();
+    database::set_invoice_status(&connection, &invoice_id.to_string(), InvoiceStatus::Issued, "2026-05-01 09:00:00").unwrap();
+    let invoices = listings(&connection);
+
+    let mut json = Vec::new();
+    output::write_invoices(&invoices, OutputFormat::Json, &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    let invoice = &json[0];
+    assert_eq!(invoice["id"], invoice_id);
+    assert_eq!(invoice["number"], "INV-2026-0001");
+    assert_eq!(invoice["status"], "issued");
+    assert_eq!(invoice["total"], "220.00");
+    assert_eq!(invoice["balance_due"], "220.00");
+    assert_eq!(invoice["items"][0]["description"], "Design");
+    assert_eq!(invoice["items"][0]["amount"], "200.00");
+    assert_eq!(invoice["items"][0]["tax_rate"]["code"], "gst");
+
+    let mut csv = Vec::new();
+    output::write_invoices(&invoices, OutputFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "id,number,client_id,profile_id,date,due_date,currency,status,issued_at,sent_at,paid_at,voided_at,total,amount_credited,amount_paid,balance_due"
+    );
+    assert_eq!(
+        lines.next().unwrap(),
+        "1,INV-2026-0001,1,,2026-05-01,2026-05-15,AUD,issued,2026-05-01 09:00:00,,,,220.00,0.00,0.00,220.00"
+    );
+}
+
+#[test]
+fn test_write_numbering_json() {
+    let sequence = NumberSequence::new(None, DocumentType::CreditNote);
+    let mut json = Vec::new();
+    output::write_numbering(&sequence, 2026, OutputFormat::Json, &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["document_type"], "credit_note");
+    assert_eq!(json["prefix"], "CN-");
+    assert_eq!(json["next"], "CN-2026-0001");
+}
+
+#[test]
+fn test_write_config_formats() {
+    let config = clinv::config::Config::default();
+
+    let mut csv = Vec::new();
+    output::write_config(&config, OutputFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next().unwrap(), "key,value");
+    assert_eq!(lines.next().unwrap(), "template,default");
+    assert!(csv.contains("currency,AUD\n"));
+
+    let mut json = Vec::new();
+    output::write_config(&config, OutputFormat::Json, &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), clinv::config::KEYS.len());
+    assert_eq!(json[0]["key"], "template");
+
+    let mut table = Vec::new();
+    output::write_config(&config, OutputFormat::Table, &mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.contains(&format!("pdf_backend = {}\n", clinv::pdf::PdfBackend::default())));
+}
+
+#[test]
+fn test_write_templates_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("plain.html"), "{{ number }}").unwrap();
+    let config = clinv::config::Config {
+        template: "plain".into(),
+        template_dir: dir.path().to_path_buf(),
+        ..Default::default()
+    };
+
+    let mut csv = Vec::new();
+    output::write_templates(&config, OutputFormat::Csv, &mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), "name,builtin,configured\ndefault,true,false\nplain,false,true\n");
+
+    let mut json = Vec::new();
+    output::write_templates(&config, OutputFormat::Json, &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json[1]["name"], "plain");
+    assert_eq!(json[1]["configured"], true);
+
+    let mut table = Vec::new();
+    output::write_templates(&config, OutputFormat::Table, &mut table).unwrap();
+    assert_eq!(String::from_utf8(table).unwrap(), "  default (built-in)\n* plain\n");
+}
@@ -0,0 +1,127 @@
+use clinv::config::Config;
+use clinv::error::Error;
+use clinv::models::InvoiceForPdf;
+use clinv::pdf::{PdfBackend, Renderer, Source};
+use clinv::template::Template;
+use clinv::utils::generate_pdf;
+use std::cell::RefCell;
+use std::path::Path;
+
+mod common;
+
+/// Keeps whatever it's asked to render
+struct Recorder {
+    source: Source,
+    document: RefCell<String>,
+}
+
+impl Renderer for Recorder {
+    fn source(&self) -> Source {
+        self.source
+    }
+
+    fn render(&self, _title: &str, document: &str, _path: &Path) -> Result<(), Error> {
+        *self.document.borrow_mut() = document.to_string();
+        Ok(())
+    }
+}
+
+fn invoice() -> InvoiceForPdf {
+    common::invoice(vec![common::item("Design & build", 2.0, 12000, common::gst())])
+}
+
+#[test]
+fn test_text_renderers_use_the_text_layout() {
+    let dir = tempfile::tempdir().unwrap();
+    let recorder = Recorder { source: Source::Text, document: RefCell::new(String::new()) };
+
+    // The HTML template isn't needed
+    let path = generate_pdf(&invoice(), &Template::File("./no-such-template.html".into()), dir.path(), &recorder).unwrap();
+    assert_eq!(path, dir.path().join("2026-03").join("02-7-Alice.pdf"));
+
+    let document = recorder.document.borrow();
+    assert!(document.contains("Invoice #: INV-2026-0007"));
+    // Text isn't HTML-escaped
+    assert!(document.contains("Design & build"));
+    assert!(document.contains("$264.00"));
+    assert!(!document.contains("<td>"));
+}
+
+#[test]
+fn test_html_renderers_use_the_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let recorder = Recorder { source: Source::Html, document: RefCell::new(String::new()) };
+
+    generate_pdf(&invoice(), &Template::File("template.html".into()), dir.path(), &recorder).unwrap();
+    assert!(recorder.document.borrow().contains("<td>Design &amp; build</td>"));
+}
+
+#[test]
+fn test_pdf_backend_setting() {
+    assert_eq!("auto".parse::<PdfBackend>(), Ok(PdfBackend::Auto));
+    assert_eq!("builtin".parse::<PdfBackend>(), Ok(PdfBackend::Builtin));
+    assert_eq!("WkHtmlToPdf".parse::<PdfBackend>(), Ok(PdfBackend::Wkhtmltopdf));
+    assert!("latex".parse::<PdfBackend>().is_err());
+
+    let mut config = Config::default();
+    assert_eq!(config.pdf_backend, PdfBackend::Auto);
+    config.set("pdf_backend", "wkhtmltopdf").unwrap();
+    assert_eq!(config.pdf_backend, PdfBackend::Wkhtmltopdf);
+    assert_eq!(config.get("pdf_backend").unwrap(), "wkhtmltopdf");
+    assert!(config.set("pdf_backend", "latex").is_err());
+}
+
+#[cfg(feature = "printpdf")]
+#[test]
+fn test_builtin_backend_writes_pdf() {
+    let dir = tempfile::tempdir().unwrap();
+    let renderer = clinv::pdf::renderer(PdfBackend::Builtin, false).unwrap();
+    assert_eq!(renderer.source(), Source::Text);
+
+    // Long invoices carry on to further pages
+    let items = (0..120).map(|id| common::item(&format!("Item {}", id), 2.0, 12000, common::gst())).collect();
+    let invoice = common::invoice(items);
+
+    let path = generate_pdf(&invoice, &Template::File("unused.html".into()), dir.path(), renderer.as_ref()).unwrap();
+    let bytes = std::fs::read(path).unwrap();
+    assert!(bytes.starts_with(b"%PDF"));
+}
+
+#[cfg(feature = "printpdf")]
+#[test]
+fn test_builtin_backend_reports_characters_it_cannot_show() {
+    let dir = tempfile::tempdir().unwrap();
+    let renderer = clinv::pdf::renderer(PdfBackend::Builtin, false).unwrap();
+    let mut invoice = invoice();
+
+    // Accented and non-Latin-1 names are kept
+    invoice.client_name = "Zoë Łukasz".to_string();
+    let path = generate_pdf(&invoice, &Template::File("unused.html".into()), dir.path(), renderer.as_ref()).unwrap();
+    assert!(std::fs::read(path).unwrap().starts_with(b"%PDF"));
+
+    // Rather than dropped from the PDF
+    invoice.client_name = "Zoë Łukasz 日本".to_string();
+    let result = generate_pdf(&invoice, &Template::File("unused.html".into()), dir.path(), renderer.as_ref());
+    assert!(matches!(result, Err(Error::Pdf(message)) if message.contains("\"日本\"")));
+}
+
+#[test]
+fn test_auto_backend_keeps_custom_templates_on_wkhtmltopdf() {
+    #[cfg(feature = "printpdf")]
+    assert_eq!(clinv::pdf::renderer(PdfBackend::Auto, false).unwrap().source(), Source::Text);
+
+    let custom = clinv::pdf::renderer(PdfBackend::Auto, true);
+    if cfg!(feature = "wkhtmltopdf") {
+        assert_eq!(custom.unwrap().source(), Source::Html);
+    } else {
+        // Rather than quietly leaving the template out
+        assert!(matches!(custom, Err(Error::Pdf(message)) if message.contains("renders custom templates")));
+    }
+}
+
+#[cfg(not(feature = "wkhtmltopdf"))]
+#[test]
+fn test_missing_backend_is_reported() {
+    let result = clinv::pdf::renderer(PdfBackend::Wkhtmltopdf, false);
+    assert!(matches!(result, Err(Error::Pdf(_))));
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDate;
+use clinv::cli::OutputFormat;
+use clinv::database::{self, init_db};
+use clinv::models::{InvoiceStatus, NewItem};
+use clinv::money::Money;
+use clinv::reports::{self, AgingBucket};
+use rusqlite::Connection;
+
+fn setup() -> Connection {
+    let connection = Connection::open_in_memory().unwrap();
+    init_db(&connection).unwrap();
+    connection
+}
+
+/// Adds an invoice with a single untaxed item, moved to `status`
+fn invoice(
+    connection: &Connection,
+    client: &str,
+    due_date: &str,
+    amount_minor: i64,
+    status: InvoiceStatus,
+) -> i64 {
+    let invoice_id = database::new_invoice(connection, client, "2025-01-01", None, None, 30, "AUD").unwrap();
+    connection
+        .execute(
+            "UPDATE invoice SET due_date = ?1 WHERE id = ?2",
+            rusqlite::params![due_date, invoice_id],
+        )
+        .unwrap();
+    let rate = Money::new(amount_minor, "AUD");
+    database::add_invoice_item(connection, invoice_id, "Work", 1.0, &rate, "none").unwrap();
+
+    let id = invoice_id.to_string();
+    match status {
+        InvoiceStatus::Draft => {}
+        InvoiceStatus::Void => {
+            database::set_invoice_status(connection, &id, status, "2025-01-01").unwrap();
+        }
+        _ => {
+            for step in [
+                InvoiceStatus::Issued,
+                InvoiceStatus::Sent,
+                InvoiceStatus::Paid,
+            ] {
+                database::set_invoice_status(connection, &id, step, "2025-01-01").unwrap();
+                if step == status {
+                    break;
+                }
+            }
+        }
+    }
+    invoice_id
+}
+
+fn as_of() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 6, 30).unwrap()
+}
+
+#[test]
+fn test_bucket_boundaries() {
+    assert_eq!(AgingBucket::for_days_overdue(-5), AgingBucket::Current);
+    assert_eq!(AgingBucket::for_days_overdue(0), AgingBucket::Current);
+    assert_eq!(AgingBucket::for_days_overdue(1), AgingBucket::Days1To30);
+    assert_eq!(AgingBucket::for_days_overdue(30), AgingBucket::Days1To30);
+    assert_eq!(AgingBucket::for_days_overdue(31), AgingBucket::Days31To60);
+    assert_eq!(AgingBucket::for_days_overdue(60), AgingBucket::Days31To60);
+    assert_eq!(AgingBucket::for_days_overdue(61), AgingBucket::Days61To90);
+    assert_eq!(AgingBucket::for_days_overdue(90), AgingBucket::Days61To90);
+    assert_eq!(AgingBucket::for_days_overdue(91), AgingBucket::Over90);
+}
+
+#[test]
+fn test_aging_report_buckets_by_client() {
+    let connection = setup();
+    database::new_client(&connection, "Alice", "al", "al@example.com", "1", None).unwrap();
+    database::new_client(&connection, "Bob", "bob", "bob@example.com", "2", None).unwrap();
+
+    invoice(&connection, "al", "2025-07-15", 10000, InvoiceStatus::Sent); // current
+    invoice(&connection, "al", "2025-06-10", 2000, InvoiceStatus::Issued); // 20 days
+    invoice(&connection, "al", "2025-03-01", 3000, InvoiceStatus::Sent); // 121 days
+    invoice(&connection, "bob", "2025-05-01", 4000, InvoiceStatus::Sent); // 60 days
+                                                                          // Not outstanding
+    invoice(&connection, "bob", "2025-01-01", 9900, InvoiceStatus::Draft);
+    invoice(&connection, "bob", "2025-01-01", 9900, InvoiceStatus::Void);
+    invoice(&connection, "bob", "2025-01-01", 9900, InvoiceStatus::Paid);
+
+    let report = reports::aging_report(&connection, as_of()).unwrap();
+    assert_eq!(report.as_of, "2025-06-30");
+    assert_eq!(report.rows.len(), 2);
+
+    let al = &report.rows[0];
+    assert_eq!(al.client, "al");
+    assert_eq!(al.current, Money::new(10000, "AUD"));
+    assert_eq!(al.days_1_30, Money::new(2000, "AUD"));
+    assert_eq!(al.days_90_plus, Money::new(3000, "AUD"));
+    assert_eq!(al.total, Money::new(15000, "AUD"));
+
+    let bob = &report.rows[1];
+    assert_eq!(bob.days_31_60, Money::new(4000, "AUD"));
+    assert_eq!(bob.total, Money::new(4000, "AUD"));
+}
+
+#[test]
+fn test_aging_report_uses_balance_after_payments() {
+    let connection = setup();
+    database::new_client(&connection, "Alice", "al", "al@example.com", "1", None).unwrap();
+    let partly_paid = invoice(&connection, "al", "2025-06-01", 10000, InvoiceStatus::Sent);
+    let fully_paid = invoice(&connection, "al", "2025-06-01", 5000, InvoiceStatus::Sent);
+    database::record_payment(
+        &connection,
+        partly_paid as i32,
+        "2025-06-02",
+        &Money::new(2500, "AUD"),
+        "cash",
+        None,
+    )
+    .unwrap();
+    database::record_payment(
+        &connection,
+        fully_paid as i32,
+        "2025-06-02",
+        &Money::new(5000, "AUD"),
+        "cash",
+        None,
+    )
+    .unwrap();
+
+    let report = reports::aging_report(&connection, as_of()).unwrap();
+    assert_eq!(report.rows.len(), 1);
+    assert_eq!(report.rows[0].days_1_30, Money::new(7500, "AUD"));
+}
+
+#[test]
+fn test_aging_report_uses_balance_after_credit_notes() {
+    let connection = setup();
+    database::new_client(&connection, "Alice", "al", "al@example.com", "1", None).unwrap();
+    let partly_credited = invoice(&connection, "al", "2025-06-01", 10000, InvoiceStatus::Sent);
+    let fully_credited = invoice(&connection, "al", "2025-06-01", 5000, InvoiceStatus::Issued);
+    for (invoice_id, amount_minor) in [(partly_credited, 4000), (fully_credited, 5000)] {
+        let item = NewItem {
+            description: "Overbilled".to_string(),
+            hours: 1.0,
+            rate: Money::new(amount_minor, "AUD"),
+            tax_code: "none".to_string(),
+        };
+        database::new_credit_note(&connection, invoice_id as i32, "2025-06-02", "Overbilled", &[item])
+            .unwrap();
+    }
+
+    let report = reports::aging_report(&connection, as_of()).unwrap();
+    assert_eq!(report.rows.len(), 1);
+    assert_eq!(report.rows[0].days_1_30, Money::new(6000, "AUD"));
+}
+
+#[test]
+fn test_write_aging_report_formats() {
+    let connection = setup();
+    database::new_client(&connection, "Alice", "al", "al@example.com", "1", None).unwrap();
+    invoice(&connection, "al", "2025-06-10", 2050, InvoiceStatus::Sent);
+    let report = reports::aging_report(&connection, as_of()).unwrap();
+
+    let mut csv = Vec::new();
+    reports::write_aging_report(&report, OutputFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(
+        csv,
+        "client,client_name,currency,current,days_1_30,days_31_60,days_61_90,days_90_plus,total\n\
+         al,Alice,AUD,0.00,20.50,0.00,0.00,0.00,20.50\n"
+    );
+
+    let mut json = Vec::new();
+    reports::write_aging_report(&report, OutputFormat::Json, &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["as_of"], "2025-06-30");
+    assert_eq!(json["rows"][0]["client"], "al");
+    assert_eq!(json["rows"][0]["days_1_30"], "20.50");
+
+    let mut table = Vec::new();
+    reports::write_aging_report(&report, OutputFormat::Table, &mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.contains("al"));
+    assert!(table.contains("TOTAL"));
+    assert!(table.contains("20.50"));
+}
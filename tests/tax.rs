@@ -0,0 +1,88 @@
+use clinv::models::{InvoiceItem, TaxRate};
+use clinv::money::Money;
+use clinv::tax::{compute_totals, format_percent, parse_percent};
+
+fn rate(code: &str, basis_points: i64) -> TaxRate {
+    TaxRate {
+        code: code.to_string(),
+        name: code.to_uppercase(),
+        basis_points,
+    }
+}
+
+fn item(amount_minor: i64, tax_rate: TaxRate) -> InvoiceItem {
+    InvoiceItem {
+        id: 0,
+        description: "Work".to_string(),
+        hours: 1.0,
+        rate: Money::new(amount_minor, "AUD"),
+        amount: Money::new(amount_minor, "AUD"),
+        tax_rate,
+    }
+}
+
+#[test]
+fn test_totals_with_single_rate() {
+    let items = vec![
+        item(10000, rate("gst", 1000)),
+        item(5000, rate("gst", 1000)),
+    ];
+    let totals = compute_totals(&items, "AUD");
+
+    assert_eq!(totals.subtotal, Money::new(15000, "AUD"));
+    assert_eq!(totals.tax_lines.len(), 1);
+    assert_eq!(totals.tax_lines[0].taxable, Money::new(15000, "AUD"));
+    assert_eq!(totals.tax_lines[0].tax, Money::new(1500, "AUD"));
+    assert_eq!(totals.tax_total, Money::new(1500, "AUD"));
+    assert_eq!(totals.total, Money::new(16500, "AUD"));
+}
+
+#[test]
+fn test_totals_group_by_rate() {
+    let items = vec![
+        item(10000, rate("gst", 1000)),
+        item(2000, rate("gst-free", 0)),
+        item(3000, rate("gst", 1000)),
+    ];
+    let totals = compute_totals(&items, "AUD");
+
+    assert_eq!(totals.subtotal, Money::new(15000, "AUD"));
+    assert_eq!(totals.tax_lines.len(), 2);
+    assert_eq!(totals.tax_lines[0].rate.code, "gst");
+    assert_eq!(totals.tax_lines[0].tax, Money::new(1300, "AUD"));
+    assert_eq!(totals.tax_lines[1].rate.code, "gst-free");
+    assert_eq!(totals.tax_lines[1].tax, Money::new(0, "AUD"));
+    assert_eq!(totals.total, Money::new(16300, "AUD"));
+}
+
+#[test]
+fn test_tax_rounded_once_per_rate() {
+    // 0.05 each: per-item tax would round 0.005 up three times (0.03),
+    // while tax on the 0.15 base is 0.015 -> 0.02
+    let items = vec![
+        item(5, rate("gst", 1000)),
+        item(5, rate("gst", 1000)),
+        item(5, rate("gst", 1000)),
+    ];
+    let totals = compute_totals(&items, "AUD");
+    assert_eq!(totals.tax_total, Money::new(2, "AUD"));
+}
+
+#[test]
+fn test_totals_with_no_items() {
+    let totals = compute_totals(&[], "AUD");
+    assert!(totals.tax_lines.is_empty());
+    assert_eq!(totals.total, Money::zero("AUD"));
+}
+
+#[test]
+fn test_parse_and_format_percent() {
+    assert_eq!(parse_percent("10"), Some(1000));
+    assert_eq!(parse_percent("12.5%"), Some(1250));
+    assert_eq!(parse_percent("0"), Some(0));
+    assert_eq!(parse_percent("-1"), None);
+    assert_eq!(parse_percent("101"), None);
+    assert_eq!(parse_percent("ten"), None);
+    assert_eq!(format_percent(1000), "10%");
+    assert_eq!(format_percent(1250), "12.5%");
+}
@@ -0,0 +1,260 @@
+use clinv::error::Error;
+use clinv::models::{CreditNote, DocumentType};
+use clinv::pdf::Source;
+use clinv::template::{self, CreditNoteContext, InvoiceContext, Template};
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{gst, gst_free, invoice, item};
+
+#[test]
+fn test_render_loops_and_filters() {
+    let invoice = invoice(vec![item("Design", 10.0, 150000, gst()), item("Hosting", 1.0, 2500, gst_free())]);
+    let source = "{% for item in items %}{{ item.description }}: {{ item.amount | money }}\n{% endfor %}\
+                  {% for line in tax_lines %}{{ line.rate.name }} {{ line.rate.basis_points | percent }}: {{ line.tax | money }}\n{% endfor %}\
+                  Total {{ total | money }} due {{ due_date | date }}";
+
+    let html = template::render("test", source, Source::Html, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(
+        html,
+        "Design: $15,000.00\nHosting: $25.00\nGST 10%: $1,500.00\nGST free 0%: $0.00\nTotal $16,525.00 due 1 April 2026"
+    );
+}
+
+#[test]
+fn test_render_conditionals() {
+    let source = "{% if tax_lines %}taxed{% else %}no tax{% endif %}|{% if business.abn %}ABN {{ business.abn }}{% endif %}";
+
+    let untaxed = invoice(Vec::new());
+    assert_eq!(template::render("test", source, Source::Html, InvoiceContext::new(&untaxed)).unwrap(), "no tax|");
+
+    let mut taxed = invoice(vec![item("Design", 1.0, 10000, gst())]);
+    taxed.issuer.abn = "51 824 753 556".to_string();
+    assert_eq!(template::render("test", source, Source::Html, InvoiceContext::new(&taxed)).unwrap(), "taxed|ABN 51 824 753 556");
+}
+
+#[test]
+fn test_render_date_format_and_drafts() {
+    let mut draft = invoice(Vec::new());
+    draft.number = None;
+    let source = "{{ number }} {{ date | date(\"%d/%m/%Y\") }} {{ business.address_lines | join(\", \") }}";
+    assert_eq!(
+        template::render("test", source, Source::Html, InvoiceContext::new(&draft)).unwrap(),
+        "DRAFT-7 02/03/2026 1 Main St, Sydney NSW 2000"
+    );
+}
+
+#[test]
+fn test_render_errors() {
+    let invoice = invoice(Vec::new());
+
+    // Misspelt fields are reported instead of silently left blank
+    let result = template::render("test", "{{ client.nmae }}", Source::Html, InvoiceContext::new(&invoice));
+    assert!(matches!(result, Err(Error::Template(_))));
+
+    let result = template::render("test", "{% for item in items %}", Source::Html, InvoiceContext::new(&invoice));
+    assert!(matches!(result, Err(Error::Template(_))));
+
+    let result = template::render("test", "{{ client.name | date }}", Source::Html, InvoiceContext::new(&invoice));
+    assert!(matches!(result, Err(Error::Template(_))));
+}
+
+#[test]
+fn test_bundled_templates_render() {
+    let invoice = invoice(vec![item("Design", 2.0, 12000, gst())]);
+    let source = fs::read_to_string("template.html").unwrap();
+    let html = template::render("template.html", &source, Source::Html, InvoiceContext::new(&invoice)).unwrap();
+    assert!(html.contains("Invoice #: INV-2026-0007"));
+    assert!(html.contains("<td>Design</td>"));
+    assert!(html.contains("GST (10%) on $240.00"));
+    assert!(html.contains("Balance due: $264.00"));
+    assert!(html.contains("1 Main St<br />Sydney NSW 2000<br />"));
+
+    let credit_note = CreditNote {
+        id: 1,
+        number: "CN-2026-0001".to_string(),
+        invoice_id: 7,
+        date: "2026-03-10".to_string(),
+        reason: "Overbilled".to_string(),
+        currency: "AUD".to_string(),
+        items: vec![item("Design", 1.0, 12000, gst())],
+    };
+    let source = fs::read_to_string("credit_note.html").unwrap();
+    let html = template::render("credit_note.html", &source, Source::Html, CreditNoteContext::new(&credit_note, &invoice)).unwrap();
+    assert!(html.contains("Credit Note #: CN-2026-0001"));
+    assert!(html.contains("For Invoice #: INV-2026-0007 (2 March 2026)"));
+    assert!(html.contains("Total credit: $132.00"));
+}
+
+#[test]
+fn test_render_escapes_hostile_input() {
+    let mut invoice = invoice(vec![item("Fish & chips <b>x2</b>", 1.0, 1000, gst())]);
+    invoice.client_name = "<script>alert(1)</script>".to_string();
+    invoice.client_email = "\"><img src=\"http://evil.example/track.png\">@example.com".to_string();
+    invoice.issuer.address = "1 Main St\n<iframe src='http://evil.example'></iframe>".to_string();
+    invoice.issuer.logo_path = "logo.png\" onerror=\"alert(1)".to_string();
+
+    let source = fs::read_to_string("template.html").unwrap();
+    let html = template::render("template.html", &source, Source::Html, InvoiceContext::new(&invoice)).unwrap();
+
+    assert!(!html.contains("<script>"));
+    assert!(html.contains("&lt;script&gt;alert(1)&lt;&#x2f;script&gt;"));
+    assert!(!html.contains("<img src=\"http://evil"));
+    assert!(html.contains("&quot;&gt;&lt;img src=&quot;http:&#x2f;&#x2f;evil.example"));
+    assert!(!html.contains("<iframe"));
+    assert!(html.contains("<td>Fish &amp; chips &lt;b&gt;x2&lt;&#x2f;b&gt;</td>"));
+    assert!(!html.contains("\" onerror=\""));
+    // The template's own markup is left alone
+    assert!(html.contains("1 Main St<br />"));
+}
+
+#[test]
+fn test_render_escapes_credit_note_reason() {
+    let invoice = invoice(Vec::new());
+    let credit_note = CreditNote {
+        id: 1,
+        number: "CN-2026-0001".to_string(),
+        invoice_id: 7,
+        date: "2026-03-10".to_string(),
+        reason: "<link rel=\"stylesheet\" href=\"http://evil.example/x.css\">".to_string(),
+        currency: "AUD".to_string(),
+        items: Vec::new(),
+    };
+    let source = fs::read_to_string("credit_note.html").unwrap();
+    let html = template::render("credit_note.html", &source, Source::Html, CreditNoteContext::new(&credit_note, &invoice)).unwrap();
+    assert!(!html.contains("<link rel"));
+    assert!(html.contains("&lt;link rel="));
+}
+
+#[test]
+fn test_render_safe_opt_out() {
+    let mut invoice = invoice(Vec::new());
+    invoice.issuer.business_name = "<em>Walker</em> Consulting".to_string();
+
+    let escaped = template::render("test", "{{ business.business_name }}", Source::Html, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(escaped, "&lt;em&gt;Walker&lt;&#x2f;em&gt; Consulting");
+
+    // Trusted fields can be marked safe in the template
+    let trusted = template::render("test", "{{ business.business_name | safe }}", Source::Html, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(trusted, "<em>Walker</em> Consulting");
+}
+
+#[test]
+fn test_escaping_follows_the_output_format() {
+    let mut invoice = invoice(Vec::new());
+    invoice.issuer.business_name = "<em>Walker</em> Consulting".to_string();
+    let source = "{{ business.business_name }}";
+
+    // A user's HTML template is escaped whatever its file is called
+    let html = template::render("notes.md", source, Source::Html, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(html, "&lt;em&gt;Walker&lt;&#x2f;em&gt; Consulting");
+
+    let text = template::render("invoice.txt", source, Source::Text, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(text, "<em>Walker</em> Consulting");
+    let markdown = template::render("invoice.md", source, Source::Markdown, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(markdown, "\\<em\\>Walker\\</em\\> Consulting");
+}
+
+#[test]
+fn test_markdown_escaping() {
+    let mut invoice = invoice(vec![item("Design | build & test \\ <b>", 1.0, 10000, gst_free())]);
+    invoice.issuer.business_name = "Walker & Sons".to_string();
+    let markdown = template::render("invoice.md", template::INVOICE_MARKDOWN, Source::Markdown, InvoiceContext::new(&invoice)).unwrap();
+    assert!(markdown.contains("| Design \\| build \\& test \\\\ \\<b\\> | 1.0 | $100.00 |"));
+    assert!(markdown.contains("Walker \\& Sons<br>\n"));
+
+    // The template itself can opt out
+    let markdown = template::render("notes.md", "{{ business.business_name | safe }}", Source::Markdown, InvoiceContext::new(&invoice)).unwrap();
+    assert_eq!(markdown, "Walker & Sons");
+
+    // Built-in layouts end with a newline
+    let credit_note = CreditNote {
+        id: 1,
+        number: "CN-2026-0001".to_string(),
+        invoice_id: 7,
+        date: "2026-03-10".to_string(),
+        reason: "Overbilled".to_string(),
+        currency: "AUD".to_string(),
+        items: vec![item("Design", 1.0, 10000, gst_free())],
+    };
+    let context = CreditNoteContext::new(&credit_note, &invoice);
+    let markdown = template::render("credit_note.md", template::CREDIT_NOTE_MARKDOWN, Source::Markdown, context).unwrap();
+    assert!(markdown.ends_with("## Reason\n\nOverbilled\n"));
+}
+
+#[test]
+fn test_resolve_and_list_templates() {
+    let dir = tempfile::tempdir().unwrap();
+    let template_dir = dir.path().join("templates");
+
+    // A missing directory just has no templates
+    assert!(template::list(&template_dir).unwrap().is_empty());
+
+    fs::create_dir(&template_dir).unwrap();
+    fs::write(template_dir.join("minimal.html"), "").unwrap();
+    fs::write(template_dir.join("branded.html"), "").unwrap();
+    fs::write(template_dir.join("notes.txt"), "").unwrap();
+    assert_eq!(template::list(&template_dir).unwrap(), vec!["branded", "minimal"]);
+
+    assert_eq!(
+        template::resolve(Path::new("minimal"), &template_dir, DocumentType::Invoice).unwrap(),
+        Template::File(template_dir.join("minimal.html"))
+    );
+    assert!(matches!(
+        template::resolve(Path::new("fancy"), &template_dir, DocumentType::Invoice),
+        Err(Error::Template(_))
+    ));
+
+    // Paths are used as they are
+    for path in ["./template.html", "other/minimal", "minimal.html"] {
+        assert_eq!(
+            template::resolve(Path::new(path), &template_dir, DocumentType::Invoice).unwrap(),
+            Template::File(path.into())
+        );
+    }
+}
+
+#[test]
+fn test_default_template_is_built_in() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Works without template.html in the working directory or template_dir
+    let invoice_template = template::resolve(Path::new("default"), dir.path(), DocumentType::Invoice).unwrap();
+    assert_eq!(invoice_template, Template::Builtin(DocumentType::Invoice));
+    let invoice = invoice(vec![item("Design", 1.0, 10000, gst())]);
+    let html = template::render(&invoice_template.name(), &invoice_template.source().unwrap(), Source::Html, InvoiceContext::new(&invoice)).unwrap();
+    assert!(html.contains("Invoice #: INV-2026-0007"));
+
+    let credit_note_template = template::resolve(Path::new("default"), dir.path(), DocumentType::CreditNote).unwrap();
+    assert_eq!(credit_note_template.source().unwrap(), template::CREDIT_NOTE_HTML);
+}
+
+#[test]
+fn test_export_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let template_dir = dir.path().join("templates");
+
+    let path = template::export(DocumentType::Invoice, "custom", &template_dir, false).unwrap();
+    assert_eq!(path, template_dir.join("custom.html"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), template::INVOICE_HTML);
+    assert_eq!(template::list(&template_dir).unwrap(), vec!["custom"]);
+
+    // Customisations aren't overwritten by accident
+    fs::write(&path, "mine").unwrap();
+    assert!(matches!(
+        template::export(DocumentType::Invoice, "custom", &template_dir, false),
+        Err(Error::Validation(_))
+    ));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "mine");
+    template::export(DocumentType::CreditNote, "custom", &template_dir, true).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), template::CREDIT_NOTE_HTML);
+
+    for name in ["default", "../escape", "custom.html"] {
+        assert!(matches!(
+            template::export(DocumentType::Invoice, name, &template_dir, false),
+            Err(Error::Validation(_))
+        ));
+    }
+}
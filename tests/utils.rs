@@ -0,0 +1,86 @@
+use clinv::error::Error;
+use clinv::pdf::{Renderer, Source};
+use clinv::template::Template;
+use clinv::utils::{format_payment_terms, generate_invoice_document, generate_pdf, is_valid_abn, is_valid_phone, parse_payment_terms};
+use std::path::Path;
+
+mod common;
+
+use common::draft_invoice;
+
+/// Stands in for wkhtmltopdf, which may not be installed
+struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn source(&self) -> Source {
+        Source::Html
+    }
+
+    fn render(&self, _title: &str, _document: &str, _path: &Path) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_payment_terms() {
+    assert_eq!(parse_payment_terms("net14"), Some(14));
+    assert_eq!(parse_payment_terms("Net 30"), Some(30));
+    assert_eq!(parse_payment_terms("7"), Some(7));
+    assert_eq!(parse_payment_terms("receipt"), Some(0));
+    assert_eq!(parse_payment_terms("Due on receipt"), Some(0));
+    assert_eq!(parse_payment_terms("net-5"), None);
+    assert_eq!(parse_payment_terms("soon"), None);
+}
+
+#[test]
+fn test_format_payment_terms() {
+    assert_eq!(format_payment_terms(0), "Due on receipt");
+    assert_eq!(format_payment_terms(14), "Net 14");
+}
+
+#[test]
+fn test_is_valid_phone_for_region() {
+    assert!(is_valid_phone("0412345678", "AU"));
+    assert!(is_valid_phone("021 123 4567", "NZ"));
+    // International numbers are accepted whatever the region
+    assert!(is_valid_phone("+64 21 123 4567", "AU"));
+    assert!(!is_valid_phone("phone", "AU"));
+    assert!(!is_valid_phone("0412345678", "Australia"));
+}
+
+#[test]
+fn test_is_valid_abn() {
+    assert!(is_valid_abn("51824753556"));
+    assert!(is_valid_abn("51 824 753 556"));
+    assert!(!is_valid_abn("51824753557"));
+    assert!(!is_valid_abn("5182475355"));
+    assert!(!is_valid_abn("ABN 51824753556"));
+}
+
+#[test]
+fn test_generate_pdf_missing_template() {
+    let invoice = draft_invoice(Vec::new());
+    let result = generate_pdf(&invoice, &Template::File("./no-such-template.html".into()), Path::new("invoices"), &HtmlRenderer);
+    assert!(matches!(result, Err(Error::Template(_))));
+}
+
+#[test]
+fn test_generate_invoice_document() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut invoice = draft_invoice(Vec::new());
+    invoice.client_name = "Alice & Co".to_string();
+
+    let path = generate_invoice_document(&invoice, Source::Html, &Template::File("template.html".into()), dir.path()).unwrap();
+    assert_eq!(path, dir.path().join("2026-03").join("02-7-Alice-&-Co.html"));
+    assert!(std::fs::read_to_string(&path).unwrap().contains("Alice &amp; Co"));
+
+    // Text and Markdown don't read the HTML template
+    let missing = &Template::File("./no-such-template.html".into());
+    let path = generate_invoice_document(&invoice, Source::Text, missing, dir.path()).unwrap();
+    assert_eq!(path.extension().unwrap(), "txt");
+    assert!(std::fs::read_to_string(&path).unwrap().contains("Alice & Co"));
+
+    let path = generate_invoice_document(&invoice, Source::Markdown, missing, dir.path()).unwrap();
+    assert_eq!(path.extension().unwrap(), "md");
+    assert!(std::fs::read_to_string(&path).unwrap().contains("# Invoice DRAFT-7"));
+}